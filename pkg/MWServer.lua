@@ -646,7 +646,6 @@ function MWServer:newEnvironment()
 		"error",
 		"getmetatable",
 		"ipairs",
-    "loadstring",
 		"next",
 		"pairs",
 		"pcall",
@@ -679,6 +678,22 @@ function MWServer:newEnvironment()
 		return self:tostring( val )
 	end
 	env.string.dump = nil
+	-- Chunks made by the plain loadstring would run in the global environment,
+	-- so they get the caller's instead. Whether modules keep it is up to the host.
+	env.loadstring = function( text, chunkName )
+		if type( text ) == 'string' and string.find( text, '\27Lua', 1, true ) then
+			return nil, 'cannot load code with a Lua binary chunk marker escape sequence in it'
+		end
+		local chunk, errorMsg = loadstring( text, chunkName )
+		if chunk then
+			local callerEnv = getfenv( 2 )
+			if callerEnv == nil or self.protectedEnvironments[callerEnv] then
+				callerEnv = env
+			end
+			setfenv( chunk, callerEnv )
+		end
+		return chunk, errorMsg
+	end
 	env.setfenv, env.getfenv = mw.makeProtectedEnvFuncs(
 		self.protectedEnvironments, self.protectedFunctions )
 	env.debug = {
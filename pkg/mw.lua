@@ -6,6 +6,7 @@ local php
 local allowEnvFuncs = false
 local logBuffer = ''
 local loadedData = {}
+local loadedDataVersions = {}
 local executeFunctionDepth = 0
 
 --- Put an isolation-friendly package module into the specified environment
@@ -750,6 +751,14 @@ local function validateData( d, seen )
 end
 
 function mw.loadData( module )
+	-- The data is kept across #invokes, until the host says the module source changed
+	local version = php.dataVersion( module )
+	if version ~= loadedDataVersions[module] then
+		loadedData[module] = nil
+		packageCache[module] = nil
+		loadedDataVersions[module] = version
+	end
+
 	local data = loadedData[module]
 	if type( data ) == 'string' then
 		-- No point in re-validating
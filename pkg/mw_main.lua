@@ -1,8 +1,7 @@
-package.path = arg[1] .. '/?.lua;'
-   .. '/tmp/widictor/modules/?.lua'
+-- the module search path is set up by the host through LUA_PATH
+package.path = arg[1] .. '/?.lua;' .. package.path
 
 require('MWServer')
 require('mwInit')
 server = MWServer:new( arg[2], arg[3] )
 server:execute()
-
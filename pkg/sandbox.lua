@@ -0,0 +1,71 @@
+-- Applies the host's sandbox policy. This chunk runs in the base environment, the one
+-- mw.executeModule clones for every module, so whatever it takes out of _G here no
+-- module will ever see.
+
+-- Everything that reaches outside the interpreter or around its environments.
+local dangerous = {
+	'io', 'dofile', 'loadfile', 'load', 'module', 'newproxy', 'collectgarbage', 'gcinfo', 'coroutine',
+	'os.execute', 'os.exit', 'os.getenv', 'os.remove', 'os.rename', 'os.tmpname', 'os.setlocale',
+	'debug.debug', 'debug.getfenv', 'debug.setfenv', 'debug.gethook', 'debug.sethook', 'debug.getinfo',
+	'debug.getlocal', 'debug.setlocal', 'debug.getmetatable', 'debug.setmetatable', 'debug.getregistry',
+	'debug.getupvalue', 'debug.setupvalue', 'string.dump', 'package.loadlib', 'package.cpath',
+}
+
+-- The part of the above that only touches the filesystem or the process.
+local filesystem = {
+	io = true, dofile = true, loadfile = true, ['package.loadlib'] = true,
+	['os.execute'] = true, ['os.exit'] = true, ['os.getenv'] = true, ['os.remove'] = true,
+	['os.rename'] = true, ['os.tmpname'] = true, ['os.setlocale'] = true,
+}
+
+local function lookup( path )
+	local value = _G
+	for name in string.gmatch( path, '[^.]+' ) do
+		if type( value ) ~= 'table' then
+			return nil
+		end
+		value = rawget( value, name )
+	end
+	return value
+end
+
+local function remove( path )
+	local parentPath, name = string.match( path, '^(.-)%.?([^.]+)$' )
+	local parent = parentPath == '' and _G or lookup( parentPath )
+	if type( parent ) == 'table' then
+		rawset( parent, name, nil )
+	end
+end
+
+--- Strips the base environment down to the policy and audits the result
+--
+-- @param options Table with keys:
+--   - globals: set of the global names that may stay
+--   - filesystem: whether filesystem and process functions may stay
+-- @return Sorted list of the globals taken out
+-- @return Sorted list of the dangerous names still reachable
+return function ( options )
+	local removed = {}
+	for name in pairs( _G ) do
+		if not options.globals[name] then
+			removed[#removed + 1] = name
+		end
+	end
+	for _, name in ipairs( removed ) do
+		_G[name] = nil
+	end
+	if not options.filesystem then
+		for path in pairs( filesystem ) do
+			remove( path )
+		end
+	end
+
+	local exposed = {}
+	for _, path in ipairs( dangerous ) do
+		if lookup( path ) ~= nil and not ( options.filesystem and filesystem[path] ) then
+			exposed[#exposed + 1] = path
+		end
+	end
+	table.sort( removed )
+	return removed, exposed
+end
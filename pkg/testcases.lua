@@ -0,0 +1,45 @@
+-- Runs a ScribuntoUnit suite the way Module:ScribuntoUnit does on-wiki: every function
+-- of the suite whose name starts with "test", in name order, each in its own protected
+-- call. This chunk runs in the base environment; the testcases module gets a fresh one
+-- from mw.executeModule, like any module that is #invoke'd.
+
+-- ScribuntoUnit fails an assertion by raising a table, anything else is a plain error
+local function describe( err )
+	if type( err ) == 'table' and err.ScribuntoUnit then
+		local text = tostring( err.text or 'assertion failed' )
+		if err.message ~= nil then
+			text = tostring( err.message ) .. ': ' .. text
+		end
+		return text
+	end
+	return tostring( err )
+end
+
+--- Runs the suite the testcases module returns
+--
+-- @param chunk The testcases module
+-- @param frame Frame handed to the module and to every test
+-- @return List of { name = string, passed = boolean, message = string|nil }
+return function ( chunk, frame )
+	local _, suite = mw.executeModule( chunk, nil, frame )
+	if type( suite ) ~= 'table' then
+		error( 'the testcases module returned ' .. type( suite ) .. ' instead of a suite', 0 )
+	end
+
+	local names = {}
+	for name, test in pairs( suite ) do
+		if type( name ) == 'string' and string.find( name, '^test' ) and type( test ) == 'function' then
+			names[#names + 1] = name
+		end
+	end
+	table.sort( names )
+
+	local results = {}
+	for i, name in ipairs( names ) do
+		local passed, message = xpcall( function ()
+			suite[name]( suite, frame )
+		end, describe )
+		results[i] = { name = name, passed = passed, message = message }
+	end
+	return results
+end
@@ -0,0 +1,52 @@
+use directories::ProjectDirs;
+use std::path::{Path, PathBuf};
+
+// without a home directory everything goes where it always went
+const FALLBACK: &str = "/tmp/widictor";
+
+fn project() -> Option<ProjectDirs> {
+  ProjectDirs::from("", "", "widictor")
+}
+
+/// Downloaded pages, templates and modules: `~/.cache/widictor` on Linux.
+pub fn cache() -> PathBuf {
+  project().map(|it| it.cache_dir().to_owned()).unwrap_or_else(|| FALLBACK.into())
+}
+
+/// Modules fetched from the wiki, with their `.proto` files.
+pub fn modules() -> PathBuf {
+  cache().join("modules")
+}
+
+/// Templates fetched from the wiki, one file per name as the page calls it.
+pub fn templates() -> PathBuf {
+  cache().join("templates")
+}
+
+/// Lua side of the bridge: `MWServer.lua`, `mw_main.lua` and the `mw` libraries. Taken from the data
+/// directory when installed there, see `shipped` for where else it is looked for.
+pub fn pkg() -> PathBuf {
+  shipped(project().map(|it| it.data_dir().join("pkg")), "pkg")
+}
+
+/// Settings, `sections.json` and the like: `~/.config/widictor` on Linux, see `shipped` for where else
+/// they are looked for.
+pub fn config() -> PathBuf {
+  shipped(project().map(|it| it.config_dir().to_owned()), "config/widictor")
+}
+
+// what the checkout ships, `relative` to it: where it is installed, next to the executable, or in the
+// source tree this was built from, whichever is there first. When none is, the installed location is
+// given, so whatever fails to find a file there names where it is expected.
+fn shipped(installed: Option<PathBuf>, relative: &str) -> PathBuf {
+  let executable = std::env::current_exe().ok().and_then(|it| Some(it.parent()?.join(relative)));
+  let source = Path::new(env!("CARGO_MANIFEST_DIR")).join(relative);
+  installed
+    .iter()
+    .cloned()
+    .chain(executable)
+    .chain(Some(source))
+    .find(|it| it.is_dir())
+    .or(installed)
+    .unwrap_or_else(|| Path::new(FALLBACK).join(relative))
+}
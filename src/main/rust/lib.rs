@@ -1,6 +1,6 @@
 use std::ptr::null_mut;
 
-use jni::{JNIEnv, objects::{JClass, JString}};
+use jni::{JNIEnv, objects::{JClass, JString}, sys::jboolean};
 use jnino::*;
 use wiki::Telua;
 
@@ -30,6 +30,7 @@ impl JRef for JClass<'_> {
   }
 }
 
+mod dirs;
 mod remote;
 mod scribunto;
 mod wiki;
@@ -41,6 +42,12 @@ pub extern "system" fn Java_org_apqm_jni_NativeLib_getPage(jenv: JNIEnv, _jclass
   wiki::scan(&path, &language);
 }
 
+#[no_mangle]
+pub extern "system" fn Java_org_apqm_jni_NativeLib_runTests(jenv: JNIEnv, _jclass: JClass, module: JString) -> jboolean {
+  let module: String = jenv.get_string(module).unwrap().into();
+  wiki::run_tests(&module) as jboolean
+}
+
 #[no_mangle]
 pub extern "system" fn Java_org_apqm_jni_Telua_nnew(
   jenv: JNIEnv,
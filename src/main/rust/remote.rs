@@ -37,10 +37,11 @@ struct ApiRevision {
   data: String,
 }
 
-pub fn get(page: &str) -> Result<String, Error> {
+/// The wikitext of `page` on the Wiktionary of the `edition` language.
+pub fn get(edition: &str, page: &str) -> Result<String, Error> {
   let resp = reqwest::blocking::get(&format!(
-    "https://en.wiktionary.org/w/api.php?action=query&prop=revisions&rvprop=content&format=json&titles={}",
-    page
+    "https://{}.wiktionary.org/w/api.php?action=query&prop=revisions&rvprop=content&format=json&titles={}",
+    edition, page
   ))?;
   let resp: ApiAnswer = serde_json::from_reader(resp.bytes().unwrap().as_ref())?;
   Ok(resp.query.pages.iter().last().ok_or_else(|| Error::LackOfData)?.1.revisions[0].data.clone())
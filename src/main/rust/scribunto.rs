@@ -1,7 +1,16 @@
+pub use builder::LuaBuilder;
+use chunk_tracker::ChunkTracker;
+pub use convert::LuaTypeError;
+pub use engine::{LuaCallback, LuaEngine};
+#[cfg(test)]
+pub use fake_server::FakeServer;
+pub use limits::Limits;
+use limits::Watchdog;
 pub use lua_bool::LuaBool;
 pub use lua_chunk::LuaChunk;
 pub use lua_float::LuaFloat;
 pub use lua_integer::LuaInteger;
+pub use lua_key::LuaKey;
 pub use lua_null::LuaNull;
 pub use lua_string::LuaString;
 pub use lua_table::AnyLua;
@@ -10,24 +19,45 @@ use nom::{
   bytes::complete::{tag, take_while1},
   IResult,
 };
+use replay::{Direction, ReplayReader, ReplayWriter};
+pub use replay::{Recorder, Replay};
+pub use sandbox::Sandbox;
 use std::{
-  collections::HashMap,
-  fmt::Display,
+  collections::{hash_map::DefaultHasher, HashMap},
+  hash::{Hash, Hasher},
   io::{Read, Write},
+  ops::{Deref, DerefMut},
   path::PathBuf,
-  process::{ChildStdin, ChildStdout},
-  sync::Arc,
+  process::{Child, ChildStdin, ChildStdout},
+  rc::Rc,
+  sync::{Arc, Mutex},
+  time::{Duration, Instant, SystemTime},
 };
 
+mod builder;
+mod chunk_tracker;
+mod convert;
+mod engine;
+#[cfg(test)]
+mod fake_server;
+mod limits;
+mod literal;
 mod lua_bool;
 mod lua_chunk;
 mod lua_float;
 mod lua_integer;
+mod lua_key;
 mod lua_null;
 mod lua_string;
 mod lua_table;
 mod php_error;
+mod replay;
+mod sandbox;
+mod scribunto_error;
 use php_error::PhpError;
+pub use scribunto_error::{Limit, LuaFailed, ScribuntoError};
+
+pub type ScribuntoResult<T> = Result<T, ScribuntoError>;
 
 #[macro_export]
 macro_rules! transparent_lua {
@@ -52,15 +82,22 @@ macro_rules! transparent_lua {
 
 pub struct LuaSender<W: Write> {
   writer: W,
+  recorder: Option<Recorder>,
 }
 impl<W: Write> From<W> for LuaSender<W> {
   fn from(writer: W) -> Self {
-    Self { writer }
+    Self { writer, recorder: None }
   }
 }
 impl<W: Write> LuaSender<W> {
+  pub fn record(&mut self, recorder: Recorder) {
+    self.recorder = Some(recorder);
+  }
   pub fn encode(&mut self, message: ToLuaMessage) -> Result<(), std::io::Error> {
     let message = format!("{}", LuaTable::from(message));
+    if let Some(recorder) = self.recorder.as_ref() {
+      recorder.record(Direction::ToLua, message.as_bytes())?;
+    }
     let length = message.len();
     write!(self.writer, "{:08x}", length)?;
     write!(self.writer, "{:08x}", length * 2 - 1)?;
@@ -70,61 +107,89 @@ impl<W: Write> LuaSender<W> {
 }
 pub struct LuaReceiver<R: Read> {
   reader: R,
+  recorder: Option<Recorder>,
 }
 impl<R: Read> From<R> for LuaReceiver<R> {
   fn from(reader: R) -> Self {
-    Self { reader }
+    Self { reader, recorder: None }
   }
 }
 enum LuaResult {
-  Ret(LuaTable<LuaInteger>),
-  Call(LuaString, LuaTable<LuaInteger>),
+  Ret(LuaTable),
+  Call(LuaString, LuaTable),
 }
 impl<R: Read> LuaReceiver<R> {
-  fn hex_u32_decode(src: &[u8]) -> Result<u32, Box<dyn std::error::Error>> {
+  pub fn record(&mut self, recorder: Recorder) {
+    self.recorder = Some(recorder);
+  }
+  fn hex_u32_decode(src: &[u8]) -> ScribuntoResult<u32> {
     let raw = hex::decode(src)?;
-    assert_eq!(raw.len(), 4);
+    if raw.len() != 4 {
+      return Err(ScribuntoError::protocol(format!("bad header: {} bytes in a length", raw.len())));
+    }
     Ok(u32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]))
   }
-  fn decode(&mut self) -> Result<LuaResult, Box<dyn std::error::Error>> {
+  fn sized(table: &LuaTable, list: &str, size: &str) -> ScribuntoResult<LuaTable> {
+    let values = table.get_table(list).ok_or_else(|| ScribuntoError::protocol(format!("no `{}` in message", list)))?;
+    let n = table.get_integer(size).ok_or_else(|| ScribuntoError::protocol(format!("no `{}` in message", size)))?;
+    // trailing nils are not transferred, so the list may be shorter
+    if values.sequence_len() as i64 > *n.as_raw() {
+      return Err(ScribuntoError::protocol(format!("{} values with `{}` = {}", values.sequence_len(), size, n)));
+    }
+    Ok(values)
+  }
+  fn decode(&mut self) -> ScribuntoResult<LuaResult> {
     let buf = &mut [0u8; 8];
     self.reader.read_exact(buf)?;
     let length: u32 = Self::hex_u32_decode(buf)?;
     self.reader.read_exact(buf)?;
     let test: u32 = Self::hex_u32_decode(buf)?;
-    assert_eq!(length * 2 - 1, test);
-    let mut buf = unsafe {
-      let length = length as usize;
-      let mut s = Vec::with_capacity(length);
-      s.set_len(length);
-      s
-    };
+    if (length as u64) * 2 != test as u64 + 1 {
+      return Err(ScribuntoError::protocol(format!("bad header: length {} with check {}", length, test)));
+    }
+    let mut buf = vec![0u8; length as usize];
     self.reader.read_exact(&mut buf)?;
-    let table = std::str::from_utf8(buf.as_slice())?;
-    let table = table.replace("\\\\", "\\").replace("\\r", "\n").replace("\\\"", "\"").replace("\\n", "\r");
-    let (tail, table): (&str, LuaTable<LuaString>) = LuaTable::parse(&table).unwrap();
-    assert!(tail.is_empty());
-    let op = table.get_string("op").unwrap();
+    if let Some(recorder) = self.recorder.as_ref() {
+      recorder.record(Direction::FromLua, &buf)?;
+    }
+    let table = Self::unescape(&buf);
+    let (tail, table) = LuaTable::parse(&table)?;
+    if !tail.is_empty() {
+      return Err(ScribuntoError::protocol(format!("{} bytes after the message", tail.len())));
+    }
+    let op = table.get_string("op").ok_or_else(|| ScribuntoError::protocol("no `op` in message"))?;
     match op.as_raw() {
-      "return" => {
-        let values = table.get_integer_table("values").unwrap();
-        let nvalues = table.get_integer("nvalues").unwrap();
-        assert_eq!(*nvalues.as_raw(), values.len() as i32);
-        Ok(LuaResult::Ret(values.clone()))
+      b"return" => Ok(LuaResult::Ret(Self::sized(&table, "values", "nvalues")?)),
+      b"error" => {
+        let value = table.get("value").cloned().unwrap_or_else(|| LuaNull::default().into());
+        let trace = table.get_table("trace").unwrap_or_default();
+        Err(ScribuntoError::Lua(LuaFailed { value, trace }))
       }
-      "error" => {
-        let err = table.get_string("value").unwrap();
-        Err(Box::new(PhpError::<&str>::Lua(err.as_raw().to_owned()).into_nom()))
+      b"call" => {
+        let args = Self::sized(&table, "args", "nargs")?;
+        let id = table.get_string("id").ok_or_else(|| ScribuntoError::protocol("no `id` in call"))?;
+        Ok(LuaResult::Call(id, args))
       }
-      "call" => {
-        let args = table.get_integer_table("args").unwrap();
-        let nargs = table.get_integer("nargs").unwrap();
-        let id = table.get_string("id").unwrap();
-        assert_eq!(*nargs.as_raw(), args.len() as i32);
-        Ok(LuaResult::Call(id.clone(), args.clone()))
+      _ => Err(ScribuntoError::protocol(format!("unknown op: {}", op.to_string_lossy()))),
+    }
+  }
+  // reverts the `[\r\n\\]` replacements of `MWServer:serialize`, the rest is passed byte by byte
+  fn unescape(src: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(src.len());
+    let mut i = src.iter();
+    while let Some(&c) = i.next() {
+      if c == b'\\' {
+        match i.next() {
+          Some(b'r') => out.push(b'\r'),
+          Some(b'n') => out.push(b'\n'),
+          Some(&c) => out.push(c),
+          None => out.push(b'\\'),
+        }
+      } else {
+        out.push(c);
       }
-      s => Err(Box::new(PhpError::<&str>::UnknownOp(s.to_owned()).into_nom())),
     }
+    out
   }
 }
 
@@ -132,19 +197,19 @@ impl<R: Read> LuaReceiver<R> {
 pub struct RGetStatus {
   pub pid: u32,
   // user + system in clock ticks
-  pub time: u32,
+  pub time: u64,
   // virtual memory size in octets
-  pub vsize: u32,
+  pub vsize: u64,
   // // resident set size in octets
   // pub rss: u32,
 }
 #[derive(Debug)]
 pub struct RLoadString {
-  pub id: i32,
+  pub id: i64,
 }
 #[derive(Debug)]
 pub struct RCallLuaFunction {
-  pub functions: HashMap<String, i32>,
+  pub functions: HashMap<String, i64>,
   pub values: HashMap<String, AnyLua>,
 }
 #[derive(Debug)]
@@ -156,245 +221,385 @@ pub struct LuaInstance<R: Read, W: Write> {
   input: LuaReceiver<R>,
   output: LuaSender<W>,
   includes: Vec<PathBuf>,
-  library: HashMap<LuaString, Arc<Box<dyn Fn(&mut LuaInstance<R, W>, LuaTable<LuaInteger>) -> LuaTable<LuaInteger>>>>,
+  // sources to fall back on when a file is not in `includes`
+  sources: HashMap<String, String>,
+  library: HashMap<LuaString, Rc<LuaCallback>>,
+  // callbacks made by `wrap_function`, by the chunk that calls them
+  wrapped: HashMap<i64, LuaString>,
+  process: Option<Arc<Mutex<Child>>>,
+  limits: Limits,
+  depth: usize,
+  anonymous: usize,
+  chunks: ChunkTracker,
+  // source hashes by file, valid as long as the modification time and length stay the same
+  fingerprints: HashMap<PathBuf, (SystemTime, u64, String)>,
 }
 impl<R: Read, W: Write> LuaInstance<R, W> {
-  fn decode_ack(&mut self, src: LuaResult) -> Result<LuaTable<LuaInteger>, Box<dyn std::error::Error>> {
+  fn decode_ack(&mut self, src: LuaResult) -> ScribuntoResult<LuaTable> {
     match src {
-      LuaResult::Ret(ret) => Ok(ret),
-      LuaResult::Call(id, args) => if let Some(l) = self.library.get(&id) {
-        let l = l.clone();
-        let result = l(self, args);
-        self.output.encode(ToLuaMessage::ReturnInt { values: result })?;
+      LuaResult::Ret(ret) => {
+        ret.chunks().iter().for_each(|it| self.chunks.track(it));
+        Ok(ret)
+      }
+      LuaResult::Call(id, args) => {
+        args.chunks().iter().for_each(|it| self.chunks.track(it));
+        let callback = self.library.get(&id).cloned();
+        let result = match &callback {
+          Some(l) => l(self, args),
+          None => Err(ScribuntoError::NoSuchFunction(id.to_string_lossy().into_owned())),
+        };
+        // Lua waits for a reply either way, an error is raised where the call was made
+        let missing = match result {
+          Ok(values) => {
+            self.output.encode(ToLuaMessage::Return { values })?;
+            None
+          }
+          // the pipe is not to be trusted anymore, there is no point in answering
+          Err(e @ (ScribuntoError::Io(_) | ScribuntoError::Protocol(_))) => return Err(e),
+          Err(e) => {
+            self.output.encode(ToLuaMessage::Failure { value: e.to_string().into() })?;
+            callback.is_none().then_some(e)
+          }
+        };
         let r = self.input.decode()?;
-        self.decode_ack(r)
-      } else {
-        Err(Box::new(PhpError::<&str>::NoSuchFunction(id.as_raw().to_owned())))
+        // a missing function is the bridge's fault, even when the module caught the error
+        match (self.decode_ack(r), missing) {
+          (Err(e @ (ScribuntoError::Io(_) | ScribuntoError::Protocol(_))), _) => Err(e),
+          (_, Some(e)) => Err(e),
+          (r, None) => r,
+        }
       }
     }
   }
   pub fn weld(input: LuaReceiver<R>, output: LuaSender<W>, includes: Vec<String>) -> Self {
     let includes = includes.into_iter().map(Into::into).collect();
-    Self { input, output, includes, library: HashMap::new() }
+    Self {
+      input,
+      output,
+      includes,
+      sources: HashMap::new(),
+      library: HashMap::new(),
+      wrapped: HashMap::new(),
+      process: None,
+      limits: Default::default(),
+      depth: 0,
+      anonymous: 0,
+      chunks: Default::default(),
+      fingerprints: HashMap::new(),
+    }
+  }
+  fn locate(&self, file: &str) -> Option<PathBuf> {
+    self.includes.iter().map(|it| it.join(file)).find(|it| it.exists())
+  }
+  fn hash(source: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+  }
+  fn invoke(&mut self, id: LuaChunk, args: LuaTable) -> ScribuntoResult<LuaTable> {
+    self.output.encode(ToLuaMessage::Call { id: id.to_integer(), args })?;
+    let r = self.input.decode()?;
+    self.decode_ack(r)
+  }
+  /// Session bound to a scope, it ends when the returned guard is dropped.
+  pub fn session(&mut self) -> ChunkSession<'_, R, W> {
+    self.begin_session();
+    ChunkSession(self)
+  }
+}
+impl<R: Read, W: Write> LuaEngine for LuaInstance<R, W> {
+  fn limits(&self) -> Limits {
+    self.limits
   }
-  pub fn insert_callback(
-    &mut self,
-    op: &str,
-    lambda: Box<dyn Fn(&mut LuaInstance<R, W>, LuaTable<LuaInteger>) -> LuaTable<LuaInteger>>,
-  ) {
-    self.library.insert(op.into(), Arc::new(lambda));
+  fn set_limits(&mut self, limits: Limits) {
+    self.limits = limits;
   }
-  pub fn get_status(&mut self) -> Result<RGetStatus, Box<dyn std::error::Error>> {
+  fn insert_callback(&mut self, op: &str, lambda: LuaCallback) {
+    self.library.insert(op.into(), Rc::new(lambda));
+  }
+  fn get_status(&mut self) -> ScribuntoResult<RGetStatus> {
     self.output.encode(ToLuaMessage::GetStatus)?;
     let r = self.input.decode()?;
     let r = self.decode_ack(r)?;
-    let r = r.get_string_table(1).unwrap();
-    Ok(RGetStatus {
-      pid: *r.get_integer("pid").unwrap().as_raw() as _,
-      time: *r.get_integer("time").unwrap().as_raw() as _,
-      vsize: *r.get_integer("vsize").unwrap().as_raw() as _,
-    })
-  }
-  pub fn load_string(&mut self, name: &str, text: &str) -> Result<LuaChunk, Box<dyn std::error::Error>> {
-    let text = text.replace("\\", "\\\\").replace("\n", "\\n").replace("\r", "\\r").replace("\"", "\\\"");
+    let r = r.get_table(1).ok_or_else(|| ScribuntoError::protocol("no status available"))?;
+    let field = |name: &str| {
+      r.get_integer(name)
+        .map(|it| *it.as_raw() as _)
+        .ok_or_else(|| ScribuntoError::protocol(format!("no `{}` in status", name)))
+    };
+    Ok(RGetStatus { pid: field("pid")? as _, time: field("time")?, vsize: field("vsize")? })
+  }
+  fn load_string(&mut self, name: &str, text: &str) -> ScribuntoResult<LuaChunk> {
     self.output.encode(ToLuaMessage::LoadString { text: text.into(), name: name.into() })?;
     let r = self.input.decode()?;
     let r = self.decode_ack(r)?;
-    let r = r.get_integer(1).unwrap().to_chunk();
+    let r = r.get_integer(1).ok_or_else(|| ScribuntoError::protocol("loadString returned no chunk"))?.to_chunk();
+    self.chunks.track(&r);
     Ok(r)
-    // Ok(RLoadString {
-    //   id: *r.get_integer(1).unwrap().as_raw(),
-    // })
-  }
-  pub fn load_file(&mut self, name: &str, file: &str) -> Result<LuaChunk, Box<dyn std::error::Error>> {
-    for p in self.includes.iter() {
-      let p = p.join(file);
-      if p.exists() {
-        println!("\x1b[32mFILE: {}\x1b[0m", p.display());
-        let file = std::fs::read_to_string(p)?;
-        return self.load_string(name, &file);
+  }
+  fn load_file(&mut self, name: &str, file: &str) -> ScribuntoResult<LuaChunk> {
+    if let Some(p) = self.locate(file) {
+      println!("\x1b[32mFILE: {}\x1b[0m", p.display());
+      let file = std::fs::read_to_string(p)?;
+      return self.load_string(name, &file);
+    }
+    if let Some(source) = self.sources.get(name).cloned() {
+      return self.load_string(name, &source);
+    }
+    Err(ScribuntoError::ModuleNotFound(file.to_owned()))
+  }
+  // a hash of the contents rather than the modification time, so a replay sees the same values
+  fn fingerprint(&mut self, name: &str, file: &str) -> Option<String> {
+    let path = match self.locate(file) {
+      Some(path) => path,
+      None => return self.sources.get(name).map(|it| Self::hash(it.as_bytes())),
+    };
+    let metadata = std::fs::metadata(&path).ok()?;
+    let stamp = (metadata.modified().ok()?, metadata.len());
+    match self.fingerprints.get(&path) {
+      Some((modified, len, hash)) if (*modified, *len) == stamp => Some(hash.clone()),
+      _ => {
+        let hash = Self::hash(&std::fs::read(&path).ok()?);
+        self.fingerprints.insert(path, (stamp.0, stamp.1, hash.clone()));
+        Some(hash)
       }
     }
-    Err(format!("file {} not found", file).into())
-  }
-  pub fn call_string(&mut self, name: &str, src: &str) -> Result<LuaTable<LuaInteger>, Box<dyn std::error::Error>> {
-    let id = self.load_string(name, src)?;
-    let data = self.call(id, Default::default())?;
-    Ok(data)
-  }
-  pub fn call_file(&mut self, name: &str, file: &str) -> Result<LuaTable<LuaInteger>, Box<dyn std::error::Error>> {
-    let id = self.load_file(name, file)?;
-    let data = self.call(id, Default::default())?;
-    Ok(data)
-  }
-  pub fn call(
-    &mut self,
-    id: LuaChunk,
-    args: LuaTable<LuaInteger>,
-  ) -> Result<LuaTable<LuaInteger>, Box<dyn std::error::Error>> {
+  }
+  fn call(&mut self, id: LuaChunk, args: LuaTable) -> ScribuntoResult<LuaTable> {
     println!("call({})", id);
-    self.output.encode(ToLuaMessage::Call { id: id.to_integer(), args })?;
-    let r = self.input.decode()?;
-    self.decode_ack(r)
+    // callbacks may call back into Lua, only the outermost call is watched
+    let watchdog = match self.process.as_ref() {
+      Some(process) if self.depth == 0 => Some(Watchdog::arm(process.clone(), self.limits)),
+      _ => None,
+    };
+    self.depth += 1;
+    let result = self.invoke(id, args);
+    self.depth -= 1;
+    match watchdog.and_then(Watchdog::disarm) {
+      Some(limit) => Err(ScribuntoError::LimitExceeded(limit)),
+      None => result,
+    }
   }
-  pub fn register_library(
-    &mut self,
-    name: &str,
-    functions: LuaTable<LuaString>,
-  ) -> Result<RRegisterLibrary, Box<dyn std::error::Error>> {
+  fn register_library(&mut self, name: &str, functions: LuaTable) -> ScribuntoResult<RRegisterLibrary> {
     self.output.encode(ToLuaMessage::RegisterLibrary { name: name.into(), functions })?;
     let _ = self.input.decode()?;
     Ok(RRegisterLibrary {})
   }
-  pub fn cleanup_chunks(&mut self, owned: Vec<LuaChunk>) -> Result<RCleanupChunks, Box<dyn std::error::Error>> {
-    let mut ids = LuaTable { value: Default::default(), object: None };
+  fn wrap_function(&mut self, lambda: LuaCallback) -> ScribuntoResult<LuaChunk> {
+    // the same naming as `LuaStandaloneInterpreter::wrapPhpFunction`
+    self.anonymous += 1;
+    let id = format!("anonymous*{}", self.anonymous);
+    self.insert_callback(&id, lambda);
+    self.output.encode(ToLuaMessage::WrapPhpFunction { id: id.as_str().into() })?;
+    let r = self.input.decode()?;
+    let r = self.decode_ack(r)?;
+    let chunk = r.get_function(1).ok_or_else(|| ScribuntoError::protocol("wrapPhpFunction returned no function"))?;
+    self.wrapped.insert(*chunk.as_raw(), id.into());
+    Ok(chunk)
+  }
+  fn begin_session(&mut self) {
+    self.chunks.open();
+  }
+  fn end_session(&mut self) -> ScribuntoResult<()> {
+    let released = self.chunks.close();
+    if !released.is_empty() {
+      self.cleanup_chunks(self.chunks.live())?;
+    }
+    Ok(())
+  }
+  fn pin(&mut self, chunk: &LuaChunk) {
+    self.chunks.pin(chunk);
+  }
+  fn release(&mut self, chunks: &[LuaChunk]) -> ScribuntoResult<()> {
+    self.chunks.release(chunks);
+    self.cleanup_chunks(self.chunks.live())?;
+    Ok(())
+  }
+  fn live_chunks(&self) -> usize {
+    self.chunks.len()
+  }
+  fn cleanup_chunks(&mut self, owned: Vec<LuaChunk>) -> ScribuntoResult<RCleanupChunks> {
+    self.chunks.retain(&owned);
+    let mut ids = LuaTable::default();
     for id in owned.into_iter() {
       ids.insert_bool(*id.as_raw(), true);
     }
+    // a wrapped closure goes with the chunk that calls it
+    let library = &mut self.library;
+    self.wrapped.retain(|chunk, id| {
+      let kept = ids.get(*chunk).is_some();
+      if !kept {
+        library.remove(id);
+      }
+      kept
+    });
     self.output.encode(ToLuaMessage::CleanupChunks { ids })?;
     let _ = self.input.decode()?;
     Ok(RCleanupChunks {})
   }
-  pub fn quit(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+  fn record(&mut self, recorder: Recorder) {
+    self.input.record(recorder.clone());
+    self.output.record(recorder);
+  }
+  fn quit(&mut self) -> ScribuntoResult<()> {
     self.output.encode(ToLuaMessage::Quit)?;
     Ok(())
   }
-  pub fn test_quit(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+  fn test_quit(&mut self) -> ScribuntoResult<()> {
     self.output.encode(ToLuaMessage::Testquit)?;
     Ok(())
   }
 }
+// a spawned interpreter is asked to quit and killed if it has not after a while; either way it is waited
+// on, so nothing is left running or as a zombie
+impl<R: Read, W: Write> Drop for LuaInstance<R, W> {
+  fn drop(&mut self) {
+    let process = match self.process.take() {
+      Some(process) => process,
+      None => return,
+    };
+    let _ = self.quit();
+    let mut process = process.lock().unwrap_or_else(|e| e.into_inner());
+    let deadline = Instant::now() + Duration::from_millis(500);
+    while let Ok(None) = process.try_wait() {
+      if Instant::now() > deadline {
+        let _ = process.kill().map_err(|e| log::warn!("can't kill {}: {}", process.id(), e));
+        let _ = process.wait();
+        return;
+      }
+      std::thread::sleep(Duration::from_millis(10));
+    }
+  }
+}
+pub struct ChunkSession<'a, R: Read, W: Write>(&'a mut LuaInstance<R, W>);
+impl<R: Read, W: Write> Deref for ChunkSession<'_, R, W> {
+  type Target = LuaInstance<R, W>;
+
+  fn deref(&self) -> &Self::Target {
+    self.0
+  }
+}
+impl<R: Read, W: Write> DerefMut for ChunkSession<'_, R, W> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    self.0
+  }
+}
+impl<R: Read, W: Write> Drop for ChunkSession<'_, R, W> {
+  fn drop(&mut self) {
+    if let Err(e) = self.0.end_session() {
+      log::warn!("can't release chunks: {}", e);
+    }
+  }
+}
+impl LuaInstance<ReplayReader, ReplayWriter> {
+  /// Serves a recorded session back. Modules are taken from the recording, so their files are not needed.
+  pub fn replay<P: AsRef<std::path::Path>>(path: P) -> ScribuntoResult<Self> {
+    let replay = Replay::open(path)?;
+    let sources = replay.sources();
+    let (input, output) = replay.split();
+    let mut instance = Self::weld(input.into(), output.into(), vec![]);
+    instance.sources = sources;
+    Ok(instance)
+  }
+}
 impl LuaInstance<ChildStdout, ChildStdin> {
-  pub fn new(
-    main: &str,
-    includes: &str,
-    interpreter_id: usize,
-    int_size: usize,
-    paths: Vec<String>,
-  ) -> Result<Self, std::io::Error> {
-    let path = paths.iter().fold("?.lua".to_owned(), |acc, it| format!("{};{}/?.lua", acc, it));
-    let mut proc = std::process::Command::new("lua5.1")
-      .arg(main)
-      .arg(includes)
-      .arg(format!("{}", interpreter_id).as_str())
-      .arg(format!("{}", int_size).as_str())
-      .env("LUA_PATH", path)
-      .stdin(std::process::Stdio::piped())
-      .stdout(std::process::Stdio::piped())
-      .spawn()?;
-    let input: LuaReceiver<_> = proc.stdout.take().unwrap().into();
-    let output: LuaSender<_> = proc.stdin.take().unwrap().into();
-    Ok(Self::weld(input, output, paths))
+  pub fn builder() -> LuaBuilder {
+    LuaBuilder::default()
   }
 }
 
 struct Parser;
 impl Parser {
-  fn null_val(src: &str) -> IResult<&str, (), PhpError<&str>> {
+  fn null_val(src: &[u8]) -> IResult<&[u8], (), PhpError<&[u8]>> {
     let (src, _) = tag("N")(src)?;
     Ok((src, ()))
   }
-  fn close(src: &str) -> IResult<&str, (), PhpError<&str>> {
+  fn close(src: &[u8]) -> IResult<&[u8], (), PhpError<&[u8]>> {
     let (src, _) = tag("}")(src)?;
     Ok((src, ()))
   }
-  fn open(src: &str) -> IResult<&str, (), PhpError<&str>> {
+  fn open(src: &[u8]) -> IResult<&[u8], (), PhpError<&[u8]>> {
     let (src, _) = tag("{")(src)?;
     Ok((src, ()))
   }
-  fn separator(src: &str) -> IResult<&str, (), PhpError<&str>> {
+  fn separator(src: &[u8]) -> IResult<&[u8], (), PhpError<&[u8]>> {
     let (src, _) = tag(":")(src)?;
     Ok((src, ()))
   }
-  fn prefix(src: &str) -> IResult<&str, &str, PhpError<&str>> {
-    let (src, value) = take_while1(|s: char| s.is_ascii_alphanumeric() || s == '_')(src)?;
+  fn prefix(src: &[u8]) -> IResult<&[u8], &[u8], PhpError<&[u8]>> {
+    let (src, value) = take_while1(|s: u8| s.is_ascii_alphanumeric() || s == b'_')(src)?;
     let (src, _) = Self::separator(src)?;
     Ok((src, value))
   }
-  fn usize_val(src: &str) -> IResult<&str, usize, PhpError<&str>> {
-    let (src, val) = take_while1(|s: char| s.is_numeric())(src)?;
-    let val: usize = val.parse().map_err(PhpError::from)?;
+  fn usize_val(src: &[u8]) -> IResult<&[u8], usize, PhpError<&[u8]>> {
+    let (src, val) = take_while1(|s: u8| s.is_ascii_digit())(src)?;
+    let val: usize = std::str::from_utf8(val).map_err(PhpError::from)?.parse().map_err(PhpError::from)?;
     Ok((src, val))
   }
-  fn i32_val(src: &str) -> IResult<&str, i32, PhpError<&str>> {
-    let (src, val) = take_while1(|s: char| s.is_numeric() || s == '-')(src)?;
-    let val: i32 = val.parse().unwrap();
+  fn i64_val(src: &[u8]) -> IResult<&[u8], i64, PhpError<&[u8]>> {
+    let (src, val) = take_while1(|s: u8| s.is_ascii_digit() || s == b'-')(src)?;
+    let val: i64 = std::str::from_utf8(val).map_err(PhpError::from)?.parse().map_err(PhpError::from)?;
     Ok((src, val))
   }
-  fn f32_val(src: &str) -> IResult<&str, f32, PhpError<&str>> {
-    tag::<_, _, PhpError<&str>>("INF")(src)
-      .map(|(src, _): (&str, &str)| (src, f32::INFINITY))
-      .or_else(|_| tag::<_, _, PhpError<&str>>("-INF")(src).map(|(src, _)| (src, f32::NEG_INFINITY)))
-      .or_else(|_| tag::<_, _, PhpError<&str>>("NAN")(src).map(|(src, _)| (src, f32::NAN)))
+  fn f64_val(src: &[u8]) -> IResult<&[u8], f64, PhpError<&[u8]>> {
+    tag::<_, _, PhpError<&[u8]>>("INF")(src)
+      .map(|(src, _): (&[u8], &[u8])| (src, f64::INFINITY))
+      .or_else(|_| tag::<_, _, PhpError<&[u8]>>("-INF")(src).map(|(src, _)| (src, f64::NEG_INFINITY)))
+      .or_else(|_| tag::<_, _, PhpError<&[u8]>>("NAN")(src).map(|(src, _)| (src, f64::NAN)))
       .or_else(|_| {
-        let (src, val) = take_while1(|s: char| s.is_numeric() || s == '-' || s == ',' || s == '.')(src)?;
-        let val: f32 = val.replace(',', ".").parse().map_err(PhpError::from)?;
+        // MWServer formats doubles with `%.17g`, so exponents like `1e+20` are expected
+        let (src, val) =
+          take_while1(|s: u8| s.is_ascii_digit() || matches!(s, b'-' | b'+' | b',' | b'.' | b'e' | b'E'))(src)?;
+        let val = std::str::from_utf8(val).map_err(PhpError::from)?;
+        let val: f64 = val.replace(',', ".").parse().map_err(PhpError::from)?;
         Ok((src, val))
       })
   }
-  fn str_val(src: &str, len: usize) -> IResult<&str, String, PhpError<&str>> {
+  // PHP string lengths are in bytes and the contents are not escaped: the transport
+  // escaping is already undone by `LuaReceiver`
+  fn str_val(src: &[u8], len: usize) -> IResult<&[u8], Vec<u8>, PhpError<&[u8]>> {
     let (src, _) = tag("\"")(src)?;
     if src.len() < len {
-      return Err(PhpError::BadLength(len as _, src.len() as _).into());
+      return Err(PhpError::BadLength(src.len() as _, len as _).into());
     }
-    let out = &src[0..len].to_owned();
-    let src = &src[len..];
+    let (val, src) = src.split_at(len);
     let (src, _) = tag("\"")(src)?;
-    let mut val = String::new();
-    let mut slash = false;
-    for c in out.chars() {
-      if slash {
-        val.push(match c {
-          't' => '\t',
-          'r' => '\r',
-          'n' => '\n',
-          c => c,
-        });
-        slash = false;
-      } else {
-        if c == '\\' {
-          slash = true;
-        } else {
-          val.push(c);
-        }
-      }
-    }
-    Ok((src, val))
+    Ok((src, val.to_owned()))
   }
-  fn finite(src: &str) -> IResult<&str, (), PhpError<&str>> {
+  fn finite(src: &[u8]) -> IResult<&[u8], (), PhpError<&[u8]>> {
     tag(";")(src).map(|(src, _)| (src, ()))
   }
-  fn any_lua(src: &str) -> IResult<&str, AnyLua, PhpError<&str>> {
+  // Lua functions come as `Scribunto_LuaStandaloneInterpreterFunction` objects holding the chunk id
+  fn function(table: LuaTable) -> AnyLua {
+    match (table.object.as_deref(), table.get_integer("id")) {
+      (Some("Scribunto_LuaStandaloneInterpreterFunction"), Some(id)) => id.to_chunk().into(),
+      _ => table.into(),
+    }
+  }
+  fn any_lua(src: &[u8]) -> IResult<&[u8], AnyLua, PhpError<&[u8]>> {
     LuaString::parse(src)
-      .map(|(a, b)| -> (&str, AnyLua) { (a, b.into()) })
-      .or_else(|_| LuaBool::parse(src).map(|(a, b)| -> (&str, AnyLua) { (a, b.into()) }))
-      .or_else(|_| LuaNull::parse(src).map(|(a, b)| -> (&str, AnyLua) { (a, b.into()) }))
-      .or_else(|_| LuaInteger::parse(src).map(|(a, b)| -> (&str, AnyLua) { (a, b.into()) }))
-      .or_else(|_| LuaFloat::parse(src).map(|(a, b)| -> (&str, AnyLua) { (a, b.into()) }))
-      .or_else(|_| LuaTable::<LuaInteger>::parse(src).map(|(a, b)| -> (&str, AnyLua) { (a, b.into()) }))
-      .or_else(|_| LuaTable::<LuaString>::parse(src).map(|(a, b)| -> (&str, AnyLua) { (a, b.into()) }))
+      .map(|(a, b)| -> (&[u8], AnyLua) { (a, b.into()) })
+      .or_else(|_| LuaBool::parse(src).map(|(a, b)| -> (&[u8], AnyLua) { (a, b.into()) }))
+      .or_else(|_| LuaNull::parse(src).map(|(a, b)| -> (&[u8], AnyLua) { (a, b.into()) }))
+      .or_else(|_| LuaInteger::parse(src).map(|(a, b)| -> (&[u8], AnyLua) { (a, b.into()) }))
+      .or_else(|_| LuaFloat::parse(src).map(|(a, b)| -> (&[u8], AnyLua) { (a, b.into()) }))
+      .or_else(|_| LuaTable::parse(src).map(|(a, b)| -> (&[u8], AnyLua) { (a, Self::function(b)) }))
   }
 }
 
-pub trait LuaType: 'static + Display + std::fmt::Debug + Clone {}
-pub trait LuaNameType: LuaType + Eq + std::hash::Hash {
-  fn try_from_string(src: LuaString) -> Result<Box<Self>, LuaString>;
-  fn try_from_integer(src: LuaInteger) -> Result<Box<Self>, LuaInteger>;
-}
-
 pub enum ToLuaMessage {
   LoadString { text: LuaString, name: LuaString },
-  Call { id: LuaInteger, args: LuaTable<LuaInteger> },
-  RegisterLibrary { name: LuaString, functions: LuaTable<LuaString> },
+  Call { id: LuaInteger, args: LuaTable },
+  RegisterLibrary { name: LuaString, functions: LuaTable },
+  WrapPhpFunction { id: LuaString },
   GetStatus,
-  CleanupChunks { ids: LuaTable<LuaInteger> },
+  CleanupChunks { ids: LuaTable },
   Quit,
   Testquit,
-  Return { values: LuaTable<LuaString> },
-  ReturnInt { values: LuaTable<LuaInteger> },
+  Return { values: LuaTable },
   Failure { value: LuaString },
 }
-impl From<ToLuaMessage> for LuaTable<LuaString> {
+impl From<ToLuaMessage> for LuaTable {
   fn from(src: ToLuaMessage) -> Self {
     let mut t = Self::default();
     match src {
@@ -406,20 +611,24 @@ impl From<ToLuaMessage> for LuaTable<LuaString> {
       ToLuaMessage::Call { id, args } => {
         t.insert_string("op", "call");
         t.insert_integer("id", id);
-        t.insert_integer("nargs", args.as_ref().len() as i32);
-        t.insert_integer_table("args", args);
+        t.insert_integer("nargs", args.sequence_len() as i64);
+        t.insert_table("args", args);
       }
       ToLuaMessage::RegisterLibrary { name, functions } => {
         t.insert_string("op", "registerLibrary");
         t.insert_string("name", name);
-        t.insert_string_table("functions", LuaTable::from(functions));
+        t.insert_table("functions", functions);
+      }
+      ToLuaMessage::WrapPhpFunction { id } => {
+        t.insert_string("op", "wrapPhpFunction");
+        t.insert_string("id", id);
       }
       ToLuaMessage::GetStatus => {
         t.insert_string("op", "getStatus");
       }
       ToLuaMessage::CleanupChunks { ids } => {
         t.insert_string("op", "cleanupChunks");
-        t.insert_integer_table("ids", ids);
+        t.insert_table("ids", ids);
       }
       ToLuaMessage::Quit => {
         t.insert_string("op", "quit");
@@ -429,13 +638,8 @@ impl From<ToLuaMessage> for LuaTable<LuaString> {
       }
       ToLuaMessage::Return { values } => {
         t.insert_string("op", "return");
-        t.insert_integer("nvalues", values.len() as i32);
-        t.insert_string_table("values", values);
-      }
-      ToLuaMessage::ReturnInt { values } => {
-        t.insert_string("op", "return");
-        t.insert_integer("nvalues", values.len() as i32);
-        t.insert_integer_table("values", values);
+        t.insert_integer("nvalues", values.sequence_len() as i64);
+        t.insert_table("values", values);
       }
       ToLuaMessage::Failure { value } => {
         t.insert_string("op", "error");
@@ -447,70 +651,173 @@ impl From<ToLuaMessage> for LuaTable<LuaString> {
 }
 
 pub enum FromLuaMessage {
-  Call { id: LuaInteger, args: LuaTable<LuaString> },
+  Call { id: LuaInteger, args: LuaTable },
 }
 
 pub struct LuaResponse {
   op: String,
-  values: LuaTable<LuaInteger>,
-}
-#[derive(Debug)]
-pub struct LuaFailed {
-  value: LuaString,
-  trace: LuaTable<LuaString>,
-}
-impl Display for LuaFailed {
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    write!(f, "{}", self.value.as_raw())
-  }
+  values: LuaTable,
 }
 
 #[test]
 fn test() {
-  let (last, val) = LuaString::parse(r#"s:6:"A to Z";"#).unwrap();
+  let (last, val) = LuaString::parse(br#"s:6:"A to Z";"#).unwrap();
   assert!(last.is_empty());
   assert_eq!(val, LuaString::from("A to Z"));
-  let (last, val) = LuaFloat::parse(r"d:-1.23;").unwrap();
+  let (last, val) = LuaFloat::parse(br"d:-1.23;").unwrap();
   assert!(last.is_empty());
-  assert_eq!(f32::from(val), -1.23);
-  let (last, val) = LuaInteger::parse(r"i:-882;").unwrap();
+  assert_eq!(f64::from(val), -1.23);
+  let (last, val) = LuaInteger::parse(br"i:-882;").unwrap();
   assert!(last.is_empty());
-  assert_eq!(i32::from(val), -882);
-  let (last, val) = LuaBool::parse(r"b:0;").unwrap();
+  assert_eq!(i64::from(val), -882);
+  let (last, val) = LuaBool::parse(br"b:0;").unwrap();
   assert!(last.is_empty());
   assert!(!bool::from(val));
-  let (last, val) = LuaBool::parse(r"b:1;").unwrap();
+  let (last, val) = LuaBool::parse(br"b:1;").unwrap();
   assert!(last.is_empty());
   assert!(bool::from(val));
-  let (last, _) = LuaNull::parse(r"N;").unwrap();
+  let (last, _) = LuaNull::parse(br"N;").unwrap();
   assert!(last.is_empty());
-  let (last, val): (_, LuaTable<LuaInteger>) =
-    LuaTable::parse(r#"a:4:{i:0;b:1;i:1;N;i:2;d:-421000000;i:3;s:6:"A to Z";}"#).unwrap();
+  let (last, val): (_, LuaTable) =
+    LuaTable::parse(br#"a:4:{i:0;b:1;i:1;N;i:2;d:-421000000;i:3;s:6:"A to Z";}"#).unwrap();
   assert!(last.is_empty());
   assert!(val.object.is_none());
   {
     assert!(bool::from(val.get_bool(0).unwrap()));
     assert!(val.get_null(1).is_some());
-    assert_eq!(f32::from(val.get_float(2).unwrap()), -421000000.0);
-    assert_eq!(val.get_string(3).unwrap().as_ref(), "A to Z");
+    assert_eq!(f64::from(val.get_float(2).unwrap()), -421000000.0);
+    assert_eq!(val.get_string(3).unwrap().as_raw(), b"A to Z");
   }
-  let (last, val): (_, LuaTable<LuaString>) =
-    LuaTable::parse(r#"a:2:{i:42;b:1;s:6:"A to Z";a:3:{i:0;i:1;i:1;i:2;i:2;i:3;}}"#).unwrap();
+  let (last, val): (_, LuaTable) =
+    LuaTable::parse(br#"a:2:{i:42;b:1;s:6:"A to Z";a:3:{i:0;i:1;i:1;i:2;i:2;i:3;}}"#).unwrap();
   assert!(last.is_empty());
   assert!(val.object.is_none());
   {
-    assert!(bool::from(val.get_bool("42").unwrap()));
-    let val = val.get_integer_table("A to Z").unwrap();
+    assert!(bool::from(val.get_bool(42).unwrap()));
+    assert!(val.get_bool("42").is_none());
+    let val = val.get_table("A to Z").unwrap();
     for i in 0..=2 {
-      assert_eq!(i32::from(val.get_integer(i).unwrap()), i + 1);
+      assert_eq!(i64::from(val.get_integer(i).unwrap()), i + 1);
     }
   }
-  let (last, val): (_, LuaTable<LuaString>) =
-    LuaTable::parse(r#"O:8:"stdClass":2:{s:4:"John";d:3.14;s:4:"Jane";d:2.718;}"#).unwrap();
+  let (last, val): (_, LuaTable) =
+    LuaTable::parse(br#"O:8:"stdClass":2:{s:4:"John";d:3.14;s:4:"Jane";d:2.718;}"#).unwrap();
   assert!(last.is_empty());
   assert!(val.object.as_ref().map(|v| String::from(v.clone())) == Some("stdClass".to_owned()));
   {
-    assert_eq!(f32::from(val.get_float("John").unwrap()), 3.14);
-    assert_eq!(f32::from(val.get_float("Jane").unwrap()), 2.718);
+    assert_eq!(f64::from(val.get_float("John").unwrap()), 3.14);
+    assert_eq!(f64::from(val.get_float("Jane").unwrap()), 2.718);
   }
 }
+
+#[test]
+fn test_wide_numbers() {
+  let (last, val) = LuaInteger::parse(br"i:9007199254740992;").unwrap();
+  assert!(last.is_empty());
+  assert_eq!(i64::from(val), 9007199254740992);
+  let (last, val) = LuaFloat::parse(br"d:1.0000000000000001e-05;").unwrap();
+  assert!(last.is_empty());
+  assert_eq!(f64::from(val), 1e-5);
+  let (last, val) = LuaFloat::parse(br"d:1e+20;").unwrap();
+  assert!(last.is_empty());
+  assert_eq!(f64::from(val), 1e20);
+  assert_eq!(format!("{}", LuaFloat::from(f64::NEG_INFINITY)), "(-1/0)");
+  assert_eq!(format!("{}", LuaInteger::from(1_234_567_890_123)), "1234567890123");
+}
+
+#[cfg(test)]
+use fake_server::mwserver_message;
+
+#[test]
+fn test_binary_transport() {
+  let raw: &[u8] = b"q\"\\\r\n\xff\xfe \xd0\xb6";
+  let mut body = Vec::new();
+  body.extend_from_slice(b"a:3:{s:2:\"op\";s:6:\"return\";s:7:\"nvalues\";i:1;s:6:\"values\";a:1:{i:1;s:");
+  body.extend_from_slice(format!("{}:\"", raw.len()).as_bytes());
+  body.extend_from_slice(raw);
+  body.extend_from_slice(b"\";}}");
+  let mut receiver = LuaReceiver::from(std::io::Cursor::new(mwserver_message(&body)));
+  let values = match receiver.decode().unwrap() {
+    LuaResult::Ret(values) => values,
+    LuaResult::Call(..) => panic!("unexpected call"),
+  };
+  let value = values.get_string(1).unwrap();
+  assert_eq!(value.as_raw(), raw);
+  assert_eq!(value.as_str(), None);
+  assert_eq!(format!("{}", value), r#""q\"\\\r\n\255\254 \208\182""#);
+}
+
+#[test]
+fn test_mixed_table() {
+  let (last, val) = LuaTable::parse(br#"a:3:{s:1:"n";i:3;i:2;i:2;i:1;i:1;}"#).unwrap();
+  assert!(last.is_empty());
+  assert_eq!(val.sequence_len(), 2);
+  assert_eq!(format!("{}", val), r#"{[1]=1,[2]=2,["n"]=3}"#);
+  let (_, val) = LuaTable::parse(
+    br#"a:1:{i:1;O:42:"Scribunto_LuaStandaloneInterpreterFunction":2:{s:13:"interpreterId";i:0;s:2:"id";i:7;}}"#,
+  )
+  .unwrap();
+  assert_eq!(val.get_function(1), Some(LuaChunk::from(7)));
+  assert_eq!(format!("{}", val), "{[1]=chunks[7]}");
+  let mut args = LuaTable::default();
+  args.insert_bool(true, false);
+  args.insert_float(0.5, 2.0);
+  args.insert_string(2.0, "b");
+  args.push(LuaString::from("c"));
+  assert_eq!(format!("{}", args), r#"{[2]="b",[3]="c",[0.5]=2,[true]=false}"#);
+}
+
+#[test]
+fn test_errors() {
+  let message = mwserver_message(
+    br#"a:3:{s:2:"op";s:5:"error";s:5:"value";s:13:"bad argument!";s:5:"trace";a:1:{i:1;a:3:{s:9:"short_src";s:6:"Module";s:11:"currentline";i:12;s:4:"name";s:4:"main";}}}"#,
+  );
+  match LuaReceiver::from(std::io::Cursor::new(message)).decode() {
+    Err(ScribuntoError::Lua(e)) => assert_eq!(e.to_string(), "bad argument!\n  Module:12: in main"),
+    _ => panic!("lua error expected"),
+  }
+  let message = mwserver_message(br#"a:1:{s:2:"op";s:4:"nope";}"#);
+  assert!(matches!(LuaReceiver::from(std::io::Cursor::new(message)).decode(), Err(ScribuntoError::Protocol(_))));
+  let message = b"0000000200000002{}".to_vec();
+  assert!(matches!(LuaReceiver::from(std::io::Cursor::new(message)).decode(), Err(ScribuntoError::Protocol(_))));
+  let message = mwserver_message(br#"a:1:{s:2:"op";"#);
+  assert!(matches!(LuaReceiver::from(std::io::Cursor::new(message)).decode(), Err(ScribuntoError::Protocol(_))));
+  let message = b"00000010".to_vec();
+  assert!(matches!(LuaReceiver::from(std::io::Cursor::new(message)).decode(), Err(ScribuntoError::Io(_))));
+}
+
+#[test]
+fn test_wrap_function() {
+  let server = FakeServer::new().module("map", |lua, args| {
+    let function = args.get_function(1).unwrap();
+    let mut out = LuaTable::default();
+    for (_, value) in args.get_table(2).unwrap_or_default() {
+      let value = lua.call(&function, std::iter::once((1, value)).collect())?;
+      out.push(value.get(1).cloned().unwrap_or_else(|| LuaNull::default().into()));
+    }
+    Ok(std::iter::once((1, out)).collect())
+  });
+  let mut instance = server.spawn(vec![]);
+  let double = instance
+    .wrap_function(Box::new(|_, args| {
+      let value = args.get_integer(1).map(i64::from).unwrap_or_default();
+      Ok(std::iter::once((1, LuaInteger::from(value * 2))).collect())
+    }))
+    .unwrap();
+  let map = instance.load_string("map", "").unwrap();
+  let mut args = LuaTable::default();
+  args.insert_chunk(1, double.clone());
+  args.insert_table(2, (1..=3).map(|it| (it, LuaInteger::from(it))).collect::<LuaTable>());
+  let out = instance.call(map, args).unwrap();
+  assert_eq!(format!("{}", out), "{[1]={[1]=2,[2]=4,[3]=6}}");
+  let out = instance.call(double, std::iter::once((1, LuaInteger::from(21))).collect()).unwrap();
+  assert_eq!(out.get_integer(1), Some(LuaInteger::from(42)));
+
+  // the closures of a session are dropped with its chunks
+  let callbacks = instance.library.len();
+  instance.begin_session();
+  instance.wrap_function(Box::new(|_, _| Ok(Default::default()))).unwrap();
+  assert_eq!(instance.library.len(), callbacks + 1);
+  instance.end_session().unwrap();
+  assert_eq!(instance.library.len(), callbacks);
+}
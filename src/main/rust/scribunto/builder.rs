@@ -0,0 +1,133 @@
+#[allow(unused)]
+use crate::scribunto as m;
+use m::{Limits, LuaEngine, LuaInstance, ScribuntoResult};
+
+use std::{
+  ffi::OsString,
+  path::PathBuf,
+  process::{ChildStdin, ChildStdout, Command, Stdio},
+  sync::{Arc, Mutex},
+};
+
+// `LuaTable` holds i64 and f64, so the server is always told to use 8-byte integers
+const INT_SIZE: usize = 8;
+
+/// How to start an interpreter. The defaults run `lua5.1` on the bootstrap from `dirs::pkg` and look
+/// for modules in `dirs::modules`.
+#[derive(Debug, Clone)]
+pub struct LuaBuilder {
+  interpreter: PathBuf,
+  include: PathBuf,
+  main: Option<PathBuf>,
+  paths: Vec<PathBuf>,
+  env: Vec<(OsString, OsString)>,
+  interpreter_id: usize,
+  limits: Limits,
+}
+impl Default for LuaBuilder {
+  fn default() -> Self {
+    Self {
+      interpreter: "lua5.1".into(),
+      include: crate::dirs::pkg(),
+      main: None,
+      paths: vec![crate::dirs::modules()],
+      env: Vec::new(),
+      interpreter_id: 0,
+      limits: Default::default(),
+    }
+  }
+}
+impl LuaBuilder {
+  /// `lua5.1`, `luajit` or a path to either.
+  pub fn interpreter<P: Into<PathBuf>>(mut self, interpreter: P) -> Self {
+    self.interpreter = interpreter.into();
+    self
+  }
+  /// Directory with `MWServer.lua` and the `mw` libraries.
+  pub fn include<P: Into<PathBuf>>(mut self, include: P) -> Self {
+    self.include = include.into();
+    self
+  }
+  /// The script to run, `mw_main.lua` of the include directory by default.
+  pub fn main<P: Into<PathBuf>>(mut self, main: P) -> Self {
+    self.main = Some(main.into());
+    self
+  }
+  /// Adds a module directory, searched after the include directory and the ones added before.
+  pub fn path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+    self.paths.push(path.into());
+    self
+  }
+  /// Replaces the module directories.
+  pub fn paths<P: Into<PathBuf>, I: IntoIterator<Item = P>>(mut self, paths: I) -> Self {
+    self.paths = paths.into_iter().map(Into::into).collect();
+    self
+  }
+  pub fn env<K: Into<OsString>, V: Into<OsString>>(mut self, key: K, value: V) -> Self {
+    self.env.push((key.into(), value.into()));
+    self
+  }
+  pub fn interpreter_id(mut self, interpreter_id: usize) -> Self {
+    self.interpreter_id = interpreter_id;
+    self
+  }
+  pub fn limits(mut self, limits: Limits) -> Self {
+    self.limits = limits;
+    self
+  }
+  fn search(&self) -> Vec<PathBuf> {
+    let builtin = vec![self.include.clone(), self.include.join("ustring")];
+    builtin.into_iter().chain(self.paths.iter().cloned()).collect()
+  }
+  pub fn spawn(&self) -> ScribuntoResult<LuaInstance<ChildStdout, ChildStdin>> {
+    let search = self.search();
+    let path = search.iter().fold("?.lua".to_owned(), |acc, it| format!("{};{}/?.lua", acc, it.display()));
+    let main = self.main.clone().unwrap_or_else(|| self.include.join("mw_main.lua"));
+    // the interpreter would start and die on it, with nothing to tell why
+    if !main.is_file() {
+      let message = format!("no {}, the Lua side of the bridge is expected there", main.display());
+      return Err(std::io::Error::new(std::io::ErrorKind::NotFound, message).into());
+    }
+    let mut proc = Command::new(&self.interpreter)
+      .arg(main)
+      .arg(&self.include)
+      .arg(self.interpreter_id.to_string())
+      .arg(INT_SIZE.to_string())
+      .envs(self.env.iter().map(|(k, v)| (k, v)))
+      .env("LUA_PATH", path)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .spawn()?;
+    let input = proc.stdout.take().unwrap().into();
+    let output = proc.stdin.take().unwrap().into();
+    let search = search.into_iter().map(|it| it.to_string_lossy().into_owned()).collect();
+    let mut instance = LuaInstance::weld(input, output, search);
+    instance.process = Some(Arc::new(Mutex::new(proc)));
+    instance.set_limits(self.limits);
+    Ok(instance)
+  }
+}
+
+#[cfg(test)]
+#[test]
+fn test_locations() {
+  // found wherever the tests run from
+  assert!(crate::dirs::pkg().join("mw_main.lua").is_file());
+  match LuaBuilder::default().include("/nowhere").spawn() {
+    Err(e) => assert!(e.to_string().contains("/nowhere/mw_main.lua"), "{}", e),
+    Ok(_) => panic!("spawned without a bootstrap"),
+  }
+}
+
+#[test]
+fn test_drop() {
+  // an interpreter that never reads the quit message is killed, and waited on
+  let main = std::env::temp_dir().join(format!("widictor-stuck-{}.sh", std::process::id()));
+  std::fs::write(&main, "sleep 30\n").unwrap();
+  let instance = LuaBuilder::default().interpreter("sh").main(&main).spawn().unwrap();
+  let pid = instance.process.as_ref().unwrap().lock().unwrap().id();
+  assert!(std::path::Path::new(&format!("/proc/{}", pid)).exists());
+  drop(instance);
+  assert!(!std::path::Path::new(&format!("/proc/{}", pid)).exists());
+  std::fs::remove_file(&main).unwrap();
+}
@@ -0,0 +1,86 @@
+#[allow(unused)]
+use crate::scribunto as m;
+use m::LuaChunk;
+
+use std::collections::BTreeSet;
+
+/// Chunk ids MWServer keeps alive on our behalf. A chunk belongs to the innermost open session when it
+/// first shows up and is released when that session ends; chunks seen outside of any session stay
+/// until they are released by hand.
+#[derive(Default, Debug)]
+pub struct ChunkTracker {
+  live: BTreeSet<i64>,
+  sessions: Vec<BTreeSet<i64>>,
+}
+impl ChunkTracker {
+  pub fn track(&mut self, chunk: &LuaChunk) {
+    let id = *chunk.as_raw();
+    if self.live.insert(id) {
+      if let Some(session) = self.sessions.last_mut() {
+        session.insert(id);
+      }
+    }
+  }
+  /// Detaches a chunk from its session, so it outlives it.
+  pub fn pin(&mut self, chunk: &LuaChunk) {
+    self.sessions.iter_mut().for_each(|it| {
+      it.remove(chunk.as_raw());
+    });
+  }
+  pub fn open(&mut self) {
+    self.sessions.push(BTreeSet::new());
+  }
+  /// Ends the innermost session and tells which chunks are not needed any more.
+  pub fn close(&mut self) -> Vec<LuaChunk> {
+    let released = self.sessions.pop().unwrap_or_default();
+    self.live.retain(|it| !released.contains(it));
+    released.into_iter().map(LuaChunk::from).collect()
+  }
+  pub fn release(&mut self, chunks: &[LuaChunk]) {
+    for chunk in chunks {
+      self.live.remove(chunk.as_raw());
+      self.pin(chunk);
+    }
+  }
+  /// Keeps only the given chunks, as `cleanupChunks` does on the other side.
+  pub fn retain(&mut self, owned: &[LuaChunk]) {
+    let owned: BTreeSet<i64> = owned.iter().map(|it| *it.as_raw()).collect();
+    self.live.retain(|it| owned.contains(it));
+    self.sessions.iter_mut().for_each(|it| it.retain(|it| owned.contains(it)));
+  }
+  pub fn live(&self) -> Vec<LuaChunk> {
+    self.live.iter().cloned().map(LuaChunk::from).collect()
+  }
+  pub fn len(&self) -> usize {
+    self.live.len()
+  }
+}
+
+#[cfg(test)]
+#[test]
+fn test_chunk_tracker() {
+  let chunk = LuaChunk::from;
+  let mut tracker = ChunkTracker::default();
+  tracker.track(&chunk(1));
+  tracker.open();
+  tracker.track(&chunk(1));
+  tracker.track(&chunk(2));
+  tracker.open();
+  tracker.track(&chunk(3));
+  tracker.track(&chunk(4));
+  tracker.pin(&chunk(4));
+  assert_eq!(tracker.len(), 4);
+  assert_eq!(tracker.close(), vec![chunk(3)]);
+  assert_eq!(tracker.close(), vec![chunk(2)]);
+  assert_eq!(tracker.live(), vec![chunk(1), chunk(4)]);
+  assert_eq!(tracker.close(), vec![]);
+
+  tracker.open();
+  tracker.track(&chunk(5));
+  tracker.track(&chunk(6));
+  tracker.release(&[chunk(1), chunk(5)]);
+  tracker.retain(&[chunk(4), chunk(6), chunk(7)]);
+  assert_eq!(tracker.live(), vec![chunk(4), chunk(6)]);
+  assert_eq!(tracker.close(), vec![chunk(6)]);
+  assert_eq!(tracker.live(), vec![chunk(4)]);
+}
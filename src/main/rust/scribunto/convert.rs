@@ -0,0 +1,239 @@
+#[allow(unused)]
+use crate::scribunto as m;
+use m::{AnyLua, LuaBool, LuaFloat, LuaInteger, LuaKey, LuaNull, LuaString, LuaTable};
+
+use std::{collections::HashMap, convert::TryFrom, hash::BuildHasher};
+
+/// A Lua value that does not fit the Rust type asked for.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{expected} expected, got {found}")]
+pub struct LuaTypeError {
+  pub expected: &'static str,
+  pub found: &'static str,
+}
+impl LuaTypeError {
+  fn new(expected: &'static str, found: &AnyLua) -> Self {
+    Self { expected, found: found.type_name() }
+  }
+}
+
+impl AnyLua {
+  /// The name Lua's `type()` gives the value.
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      Self::String(_) => "string",
+      Self::Float(_) | Self::Integer(_) => "number",
+      Self::Null(_) => "nil",
+      Self::Bool(_) => "boolean",
+      Self::Chunk(_) => "function",
+      Self::Table(_) => "table",
+    }
+  }
+}
+impl LuaTable {
+  /// Converts the value at `property`, a missing one is `nil`, so only an `Option` accepts it.
+  pub fn get_as<A: Into<LuaKey>, T: TryFrom<AnyLua, Error = LuaTypeError>>(
+    &self,
+    property: A,
+  ) -> Result<T, LuaTypeError> {
+    T::try_from(self.get(property).cloned().unwrap_or_else(|| LuaNull::default().into()))
+  }
+}
+
+impl From<String> for AnyLua {
+  fn from(src: String) -> Self {
+    Self::String(src.into())
+  }
+}
+impl From<&str> for AnyLua {
+  fn from(src: &str) -> Self {
+    Self::String(src.into())
+  }
+}
+impl From<i64> for AnyLua {
+  fn from(src: i64) -> Self {
+    Self::Integer(src.into())
+  }
+}
+impl From<i32> for AnyLua {
+  fn from(src: i32) -> Self {
+    Self::Integer(LuaInteger::from(i64::from(src)))
+  }
+}
+impl From<f64> for AnyLua {
+  fn from(src: f64) -> Self {
+    Self::Float(src.into())
+  }
+}
+impl From<bool> for AnyLua {
+  fn from(src: bool) -> Self {
+    Self::Bool(src.into())
+  }
+}
+impl<T: Into<AnyLua>> From<Option<T>> for AnyLua {
+  fn from(src: Option<T>) -> Self {
+    src.map(Into::into).unwrap_or_else(|| LuaNull::default().into())
+  }
+}
+impl<T: Into<AnyLua>> From<Vec<T>> for AnyLua {
+  fn from(src: Vec<T>) -> Self {
+    Self::Table(src.into())
+  }
+}
+impl<T: Into<AnyLua>, S: BuildHasher> From<HashMap<String, T, S>> for AnyLua {
+  fn from(src: HashMap<String, T, S>) -> Self {
+    Self::Table(src.into())
+  }
+}
+// a list is numbered from 1, as Lua does
+impl<T: Into<AnyLua>> From<Vec<T>> for LuaTable {
+  fn from(src: Vec<T>) -> Self {
+    src.into_iter().enumerate().map(|(id, it)| (id + 1, it)).collect()
+  }
+}
+impl<T: Into<AnyLua>, S: BuildHasher> From<HashMap<String, T, S>> for LuaTable {
+  fn from(src: HashMap<String, T, S>) -> Self {
+    src.into_iter().collect()
+  }
+}
+
+impl TryFrom<AnyLua> for String {
+  type Error = LuaTypeError;
+  fn try_from(src: AnyLua) -> Result<Self, Self::Error> {
+    match src {
+      AnyLua::String(it) => {
+        String::from_utf8(it.to_raw()).map_err(|_| LuaTypeError { expected: "UTF-8 string", found: "string" })
+      }
+      it => Err(LuaTypeError::new("string", &it)),
+    }
+  }
+}
+impl TryFrom<AnyLua> for LuaString {
+  type Error = LuaTypeError;
+  fn try_from(src: AnyLua) -> Result<Self, Self::Error> {
+    src.as_string().ok_or_else(|| LuaTypeError::new("string", &src))
+  }
+}
+impl TryFrom<AnyLua> for i64 {
+  type Error = LuaTypeError;
+  // numbers beyond `intMax` come as floats, those with no fractional part are still integers
+  fn try_from(src: AnyLua) -> Result<Self, Self::Error> {
+    match &src {
+      AnyLua::Integer(it) => Ok(*it.as_raw()),
+      AnyLua::Float(it) if it.as_raw().fract() == 0.0 && it.as_raw().abs() < i64::MAX as f64 => Ok(*it.as_raw() as _),
+      _ => Err(LuaTypeError::new("integer", &src)),
+    }
+  }
+}
+impl TryFrom<AnyLua> for f64 {
+  type Error = LuaTypeError;
+  fn try_from(src: AnyLua) -> Result<Self, Self::Error> {
+    match &src {
+      AnyLua::Float(it) => Ok(*it.as_raw()),
+      AnyLua::Integer(it) => Ok(*it.as_raw() as _),
+      _ => Err(LuaTypeError::new("number", &src)),
+    }
+  }
+}
+impl TryFrom<AnyLua> for bool {
+  type Error = LuaTypeError;
+  fn try_from(src: AnyLua) -> Result<Self, Self::Error> {
+    src.as_bool().map(LuaBool::to_raw).ok_or_else(|| LuaTypeError::new("boolean", &src))
+  }
+}
+impl TryFrom<AnyLua> for LuaTable {
+  type Error = LuaTypeError;
+  fn try_from(src: AnyLua) -> Result<Self, Self::Error> {
+    src.as_table().ok_or_else(|| LuaTypeError::new("table", &src))
+  }
+}
+impl<T: TryFrom<AnyLua, Error = LuaTypeError>> TryFrom<AnyLua> for Option<T> {
+  type Error = LuaTypeError;
+  fn try_from(src: AnyLua) -> Result<Self, Self::Error> {
+    match src {
+      AnyLua::Null(_) => Ok(None),
+      it => T::try_from(it).map(Some),
+    }
+  }
+}
+impl<T: TryFrom<AnyLua, Error = LuaTypeError>> TryFrom<AnyLua> for Vec<T> {
+  type Error = LuaTypeError;
+  // holes up to the last index are nils, they fit an `Option` only
+  fn try_from(src: AnyLua) -> Result<Self, Self::Error> {
+    let table = LuaTable::try_from(src)?;
+    (1..=table.sequence_len()).map(|id| table.get_as(id)).collect()
+  }
+}
+impl<T: TryFrom<AnyLua, Error = LuaTypeError>, S: BuildHasher + Default> TryFrom<AnyLua> for HashMap<String, T, S> {
+  type Error = LuaTypeError;
+  // numeric keys are written the way Lua's `tostring` would
+  fn try_from(src: AnyLua) -> Result<Self, Self::Error> {
+    LuaTable::try_from(src)?
+      .into_iter()
+      .map(|(key, value)| {
+        let key = match key {
+          LuaKey::String(it) => String::try_from(AnyLua::from(it))?,
+          LuaKey::Integer(it) => it.to_string(),
+          LuaKey::Float(it) => LuaFloat::from(it).to_string(),
+          LuaKey::Bool(it) => return Err(LuaTypeError::new("string key", &AnyLua::from(it))),
+        };
+        Ok((key, T::try_from(value)?))
+      })
+      .collect()
+  }
+}
+
+/// Table literal: `lua_table! { "name" => "value", 1 => 2.5 }` with keys and values of any type
+/// convertible to `LuaKey` and `AnyLua`, or `lua_table![a, b, c]` for a list numbered from 1.
+#[macro_export]
+macro_rules! lua_table {
+  () => {
+    $crate::scribunto::LuaTable::default()
+  };
+  ($($key:expr => $value:expr),+ $(,)?) => {{
+    let mut table = $crate::scribunto::LuaTable::default();
+    $(table.insert($crate::scribunto::LuaKey::from($key), $crate::scribunto::AnyLua::from($value));)+
+    table
+  }};
+  ($($value:expr),+ $(,)?) => {{
+    let mut table = $crate::scribunto::LuaTable::default();
+    $(table.push($crate::scribunto::AnyLua::from($value));)+
+    table
+  }};
+}
+
+#[cfg(test)]
+#[test]
+fn test_convert() {
+  let table = crate::lua_table! {
+    "name" => "word",
+    "count" => 3,
+    "ratio" => 0.5,
+    "big" => 4e15,
+    "flag" => true,
+    "list" => vec!["a", "b"],
+    "holes" => crate::lua_table! { 1 => 1, 3 => 3 },
+    "nested" => crate::lua_table! { "x" => 1, 2 => "two" },
+  };
+  assert_eq!(table.get_as::<_, String>("name"), Ok("word".to_owned()));
+  assert_eq!(table.get_as::<_, i64>("count"), Ok(3));
+  assert_eq!(table.get_as::<_, f64>("count"), Ok(3.0));
+  assert_eq!(table.get_as::<_, i64>("big"), Ok(4_000_000_000_000_000));
+  assert_eq!(table.get_as::<_, i64>("ratio"), Err(LuaTypeError { expected: "integer", found: "number" }));
+  assert_eq!(table.get_as::<_, bool>("flag"), Ok(true));
+  assert_eq!(table.get_as::<_, Option<bool>>("missing"), Ok(None));
+  assert_eq!(table.get_as::<_, bool>("missing"), Err(LuaTypeError { expected: "boolean", found: "nil" }));
+  assert_eq!(table.get_as::<_, Vec<String>>("list"), Ok(vec!["a".to_owned(), "b".to_owned()]));
+  assert_eq!(table.get_as::<_, Vec<Option<i64>>>("holes"), Ok(vec![Some(1), None, Some(3)]));
+  assert!(table.get_as::<_, Vec<i64>>("holes").is_err());
+  assert_eq!(table.get_as::<_, LuaTable>("nested").map(|it| it.len()), Ok(2));
+  let nested: HashMap<String, Option<String>> =
+    HashMap::try_from(AnyLua::from(crate::lua_table! { "a" => "b", 2 => None::<&str> })).unwrap();
+  assert_eq!(nested.get("a"), Some(&Some("b".to_owned())));
+  assert_eq!(nested.get("2"), Some(&None));
+
+  assert_eq!(crate::lua_table!["a", 2, false].to_string(), r#"{[1]="a",[2]=2,[3]=false}"#);
+  assert_eq!(crate::lua_table! {}.to_string(), "{}");
+  let map: HashMap<String, i64> = std::iter::once(("k".to_owned(), 1)).collect();
+  assert_eq!(LuaTable::from(map).to_string(), r#"{["k"]=1}"#);
+}
@@ -0,0 +1,50 @@
+#[allow(unused)]
+use crate::scribunto as m;
+use m::{Limits, Recorder};
+use m::{LuaChunk, LuaTable, RCleanupChunks, RGetStatus, RRegisterLibrary, ScribuntoResult};
+
+/// Rust side of a callback registered with `registerLibrary`: receives the arguments Lua passed and
+/// returns the values to hand back, or an error Lua raises where the call was made. It gets the engine,
+/// so it may call into Lua in turn.
+pub type LuaCallback = Box<dyn Fn(&mut dyn LuaEngine, LuaTable) -> ScribuntoResult<LuaTable>>;
+
+/// An interpreter speaking the MWServer protocol. `LuaInstance` implements it over any reader and writer,
+/// be it a `lua5.1` process or the in-process `FakeServer`.
+pub trait LuaEngine {
+  fn limits(&self) -> Limits;
+  /// Limits are enforced only for a spawned interpreter, a welded one has no process to watch.
+  fn set_limits(&mut self, limits: Limits);
+  fn insert_callback(&mut self, op: &str, lambda: LuaCallback);
+  fn get_status(&mut self) -> ScribuntoResult<RGetStatus>;
+  fn load_string(&mut self, name: &str, text: &str) -> ScribuntoResult<LuaChunk>;
+  fn load_file(&mut self, name: &str, file: &str) -> ScribuntoResult<LuaChunk>;
+  /// Changes whenever the source `load_file` would load does, `None` if there is no such source.
+  fn fingerprint(&mut self, name: &str, file: &str) -> Option<String>;
+  fn call(&mut self, id: LuaChunk, args: LuaTable) -> ScribuntoResult<LuaTable>;
+  fn register_library(&mut self, name: &str, functions: LuaTable) -> ScribuntoResult<RRegisterLibrary>;
+  /// Makes a Lua function out of a closure. It is a chunk like any other, so it may be passed to Lua,
+  /// stored in tables and released; the closure is dropped along with the chunk.
+  fn wrap_function(&mut self, lambda: LuaCallback) -> ScribuntoResult<LuaChunk>;
+  /// Opens a session: chunks first seen until the matching `end_session` are released by it.
+  fn begin_session(&mut self);
+  fn end_session(&mut self) -> ScribuntoResult<()>;
+  /// Keeps a chunk alive past the session it was created in.
+  fn pin(&mut self, chunk: &LuaChunk);
+  /// Releases the chunks at once, in a single `cleanupChunks`.
+  fn release(&mut self, chunks: &[LuaChunk]) -> ScribuntoResult<()>;
+  fn live_chunks(&self) -> usize;
+  fn cleanup_chunks(&mut self, owned: Vec<LuaChunk>) -> ScribuntoResult<RCleanupChunks>;
+  /// Writes every message from now on down, see `Recorder`.
+  fn record(&mut self, recorder: Recorder);
+  fn quit(&mut self) -> ScribuntoResult<()>;
+  fn test_quit(&mut self) -> ScribuntoResult<()>;
+
+  fn call_string(&mut self, name: &str, src: &str) -> ScribuntoResult<LuaTable> {
+    let id = self.load_string(name, src)?;
+    self.call(id, Default::default())
+  }
+  fn call_file(&mut self, name: &str, file: &str) -> ScribuntoResult<LuaTable> {
+    let id = self.load_file(name, file)?;
+    self.call(id, Default::default())
+  }
+}
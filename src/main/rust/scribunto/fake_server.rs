@@ -0,0 +1,375 @@
+#[allow(unused)]
+use crate::scribunto as m;
+use m::{literal::Literal, AnyLua, LuaChunk, LuaInstance, LuaKey, LuaNull, LuaString, LuaTable};
+
+use std::{
+  collections::HashMap,
+  io::{self, Read, Write},
+  sync::{
+    mpsc::{self, Receiver, Sender},
+    Arc,
+  },
+};
+
+/// Body of a Lua function living in the fake server: gets the arguments and returns the values, or the
+/// error value to raise.
+pub type FakeFunction = Arc<dyn Fn(&mut FakeLua, LuaTable) -> Result<LuaTable, AnyLua> + Send + Sync>;
+
+/// Scripted stand-in for MWServer, running in a thread of its own. It speaks the same protocol, but
+/// `loadString` does not compile anything: the chunk gets the function scripted for its `chunkName`,
+/// or one that returns nothing.
+#[derive(Default)]
+pub struct FakeServer {
+  modules: HashMap<String, FakeFunction>,
+}
+impl FakeServer {
+  pub fn new() -> Self {
+    Self::default()
+  }
+  pub fn module<F>(mut self, name: &str, function: F) -> Self
+  where
+    F: Fn(&mut FakeLua, LuaTable) -> Result<LuaTable, AnyLua> + Send + Sync + 'static,
+  {
+    self.modules.insert(name.to_owned(), Arc::new(function));
+    self
+  }
+  /// Starts serving; the thread ends on `quit` or once the instance is dropped.
+  pub fn spawn(self, includes: Vec<String>) -> LuaInstance<FakeReader, FakeWriter> {
+    let (to_lua, from_rust) = pipe();
+    let (to_rust, from_lua) = pipe();
+    let mut lua = FakeLua {
+      input: from_rust,
+      output: to_rust,
+      modules: self.modules,
+      chunks: HashMap::new(),
+      libraries: HashMap::new(),
+      next: 1,
+    };
+    std::thread::spawn(move || lua.serve());
+    LuaInstance::weld(from_lua.into(), to_lua.into(), includes)
+  }
+}
+
+/// State of the fake interpreter, as seen by the scripted functions.
+pub struct FakeLua {
+  input: FakeReader,
+  output: FakeWriter,
+  modules: HashMap<String, FakeFunction>,
+  chunks: HashMap<i64, FakeFunction>,
+  libraries: HashMap<String, LuaTable>,
+  next: i64,
+}
+impl FakeLua {
+  /// Makes a function that may be returned to Rust.
+  pub fn function<F>(&mut self, function: F) -> LuaChunk
+  where
+    F: Fn(&mut FakeLua, LuaTable) -> Result<LuaTable, AnyLua> + Send + Sync + 'static,
+  {
+    self.insert(Arc::new(function))
+  }
+  /// Calls a function of the fake, including the ones Rust passed in.
+  pub fn call(&mut self, chunk: &LuaChunk, args: LuaTable) -> Result<LuaTable, AnyLua> {
+    let function = self.chunks.get(chunk.as_raw()).cloned();
+    let function = function.ok_or_else(|| AnyLua::from(LuaString::from(format!("no {}", chunk))))?;
+    function(self, args)
+  }
+  /// Calls a callback registered by Rust with `registerLibrary`, serving whatever Rust asks meanwhile.
+  pub fn call_php(&mut self, library: &str, name: &str, args: LuaTable) -> Result<LuaTable, AnyLua> {
+    let id = self.libraries.get(library).and_then(|it| it.get_string(name));
+    let id = id.ok_or_else(|| AnyLua::from(LuaString::from(format!("no {}.{}", library, name))))?;
+    self.call_callback(id, args)
+  }
+  fn call_callback(&mut self, id: LuaString, args: LuaTable) -> Result<LuaTable, AnyLua> {
+    let mut message = LuaTable::default();
+    message.insert_string("op", "call");
+    message.insert_string("id", id);
+    message.insert_integer("nargs", args.sequence_len() as i64);
+    message.insert_table("args", args);
+    self.send(message);
+    while let Some(message) = self.receive() {
+      match message.get_string("op").as_ref().map(LuaString::as_raw) {
+        Some(b"return") => return Ok(message.get_table("values").unwrap_or_default()),
+        Some(b"error") => return Err(message.get("value").cloned().unwrap_or_else(|| LuaNull::default().into())),
+        _ => {
+          if !self.handle(message) {
+            break;
+          }
+        }
+      }
+    }
+    Err(LuaString::from("connection closed").into())
+  }
+  fn insert(&mut self, function: FakeFunction) -> LuaChunk {
+    let id = self.next;
+    self.next += 1;
+    self.chunks.insert(id, function);
+    LuaChunk::from(id)
+  }
+  fn serve(&mut self) {
+    while let Some(message) = self.receive() {
+      if !self.handle(message) {
+        break;
+      }
+    }
+  }
+  fn handle(&mut self, message: LuaTable) -> bool {
+    let op = message.get_string("op").unwrap_or_default();
+    let values = match op.as_raw() {
+      b"loadString" => {
+        let name = message.get_string("chunkName").unwrap_or_default().to_string_lossy().into_owned();
+        let function = self.modules.get(&name).cloned().unwrap_or_else(|| Arc::new(|_, _| Ok(Default::default())));
+        let mut values = LuaTable::default();
+        values.insert_integer(1, self.insert(function).to_raw());
+        Ok(values)
+      }
+      b"call" => {
+        let id = message.get_integer("id").map(i64::from).unwrap_or_default();
+        self.call(&LuaChunk::from(id), message.get_table("args").unwrap_or_default())
+      }
+      b"registerLibrary" => {
+        let name = message.get_string("name").unwrap_or_default().to_string_lossy().into_owned();
+        // like MWServer, a library registered twice gets the functions of both
+        let library = self.libraries.entry(name).or_default();
+        for (key, id) in message.get_table("functions").unwrap_or_default().iter() {
+          library.insert(key.clone(), id.clone());
+        }
+        Ok(Default::default())
+      }
+      b"wrapPhpFunction" => {
+        let id = message.get_string("id").unwrap_or_default();
+        let function = self.insert(Arc::new(move |lua, args| lua.call_callback(id.clone(), args)));
+        Ok(std::iter::once((1, function)).collect())
+      }
+      b"cleanupChunks" => {
+        let ids = message.get_table("ids").unwrap_or_default();
+        self.chunks.retain(|id, _| ids.get(*id).is_some());
+        Ok(Default::default())
+      }
+      b"getStatus" => {
+        let mut status = LuaTable::default();
+        status.insert_integer("pid", std::process::id() as i64);
+        status.insert_integer("time", 0);
+        status.insert_integer("vsize", 0);
+        Ok(std::iter::once((1, status)).collect())
+      }
+      b"quit" | b"testquit" => return false,
+      _ => Err(LuaString::from(format!("invalid message operation: {}", op.to_string_lossy())).into()),
+    };
+    let mut reply = LuaTable::default();
+    match values {
+      Ok(values) => {
+        reply.insert_string("op", "return");
+        reply.insert_integer("nvalues", values.sequence_len() as i64);
+        reply.insert_table("values", values);
+      }
+      Err(value) => {
+        reply.insert_string("op", "error");
+        reply.insert("value", value);
+      }
+    }
+    self.send(reply);
+    true
+  }
+  fn receive(&mut self) -> Option<LuaTable> {
+    let mut header = [0u8; 16];
+    self.input.read_exact(&mut header).ok()?;
+    let length = usize::from_str_radix(std::str::from_utf8(&header[..8]).ok()?, 16).ok()?;
+    let mut body = vec![0u8; length];
+    self.input.read_exact(&mut body).ok()?;
+    match Literal::table(&body) {
+      Some(table) => Some(table),
+      None => {
+        log::warn!("fake server can't read: {}", String::from_utf8_lossy(&body));
+        None
+      }
+    }
+  }
+  fn send(&mut self, message: LuaTable) {
+    let mut body = Vec::new();
+    serialize(&AnyLua::Table(message), &mut body);
+    let _ = self.output.write_all(&mwserver_message(&body));
+  }
+}
+
+/// Frames a serialized body the way `MWServer:encodeMessage` does.
+pub fn mwserver_message(body: &[u8]) -> Vec<u8> {
+  let body = body.iter().fold(Vec::new(), |mut acc, c| {
+    match c {
+      b'\r' => acc.extend_from_slice(b"\\r"),
+      b'\n' => acc.extend_from_slice(b"\\n"),
+      b'\\' => acc.extend_from_slice(b"\\\\"),
+      c => acc.push(*c),
+    }
+    acc
+  });
+  let mut message = format!("{:08x}{:08x}", body.len(), body.len() * 2 - 1).into_bytes();
+  message.extend_from_slice(&body);
+  message
+}
+
+// what `MWServer:serialize` produces, functions included
+fn serialize(value: &AnyLua, out: &mut Vec<u8>) {
+  let string = |raw: &[u8], out: &mut Vec<u8>| {
+    out.extend_from_slice(format!("s:{}:\"", raw.len()).as_bytes());
+    out.extend_from_slice(raw);
+    out.extend_from_slice(b"\";");
+  };
+  match value {
+    AnyLua::String(it) => string(it.as_raw(), out),
+    AnyLua::Integer(it) => out.extend_from_slice(format!("i:{};", it).as_bytes()),
+    AnyLua::Float(it) => {
+      let it = f64::from(it.clone());
+      let it = match () {
+        _ if it.is_nan() => "NAN".to_owned(),
+        _ if it.is_infinite() => if it > 0.0 { "INF" } else { "-INF" }.to_owned(),
+        _ => format!("{:e}", it),
+      };
+      out.extend_from_slice(format!("d:{};", it).as_bytes())
+    }
+    AnyLua::Bool(it) => out.extend_from_slice(if bool::from(it.clone()) { b"b:1;" } else { b"b:0;" }),
+    AnyLua::Null(_) => out.extend_from_slice(b"N;"),
+    AnyLua::Chunk(it) => out.extend_from_slice(
+      format!(
+        "O:42:\"Scribunto_LuaStandaloneInterpreterFunction\":2:{{s:13:\"interpreterId\";i:0;s:2:\"id\";i:{};}}",
+        it.as_raw()
+      )
+      .as_bytes(),
+    ),
+    AnyLua::Table(it) => {
+      out.extend_from_slice(format!("a:{}:{{", it.len()).as_bytes());
+      for (key, value) in it.iter() {
+        match key {
+          LuaKey::Integer(key) => out.extend_from_slice(format!("i:{};", key).as_bytes()),
+          LuaKey::String(key) => string(key.as_raw(), out),
+          key => string(key.to_string().as_bytes(), out),
+        }
+        serialize(value, out);
+      }
+      out.push(b'}');
+    }
+  }
+}
+
+/// Reading end of an in-process pipe.
+pub struct FakeReader {
+  input: Receiver<Vec<u8>>,
+  buffer: Vec<u8>,
+  position: usize,
+}
+impl Read for FakeReader {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    if self.position == self.buffer.len() {
+      match self.input.recv() {
+        Ok(buffer) => {
+          self.buffer = buffer;
+          self.position = 0;
+        }
+        Err(_) => return Ok(0),
+      }
+    }
+    let len = buf.len().min(self.buffer.len() - self.position);
+    buf[..len].copy_from_slice(&self.buffer[self.position..self.position + len]);
+    self.position += len;
+    Ok(len)
+  }
+}
+/// Writing end of an in-process pipe.
+pub struct FakeWriter {
+  output: Sender<Vec<u8>>,
+}
+impl Write for FakeWriter {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.output.send(buf.to_owned()).map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "fake server is gone"))?;
+    Ok(buf.len())
+  }
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+fn pipe() -> (FakeWriter, FakeReader) {
+  let (output, input) = mpsc::channel();
+  (FakeWriter { output }, FakeReader { input, buffer: Vec::new(), position: 0 })
+}
+
+#[cfg(test)]
+#[test]
+fn test_fake_server() {
+  use m::LuaEngine;
+  use std::sync::Mutex;
+
+  let seen = Arc::new(Mutex::new(Vec::new()));
+  let log = seen.clone();
+  let mut instance = FakeServer::new()
+    .module("echo", |lua, _| {
+      let echo = lua.function(|lua, args| {
+        // calls back into Rust, which calls this very function again
+        let mut values = lua.call_php("probe", "twice", args)?;
+        values.push(LuaString::from("echo"));
+        Ok(values)
+      });
+      Ok(std::iter::once((1, echo)).collect())
+    })
+    .module("broken", |_, _| Err(LuaString::from("broken!").into()))
+    .module("caller", |lua, _| {
+      // whatever Lua gets from the callback, the error caught
+      let caller = lua.function(|lua, args| {
+        let name = args.get_string(1).unwrap_or_default().to_string_lossy().into_owned();
+        Ok(std::iter::once((1, lua.call_php("probe", &name, Default::default()).err().unwrap())).collect())
+      });
+      Ok(std::iter::once((1, caller)).collect())
+    })
+    .spawn(vec![]);
+  instance.insert_callback(
+    "probe-twice",
+    Box::new(move |engine, args| {
+      log.lock().unwrap().push(args.to_string());
+      match args.get_integer(1).map(i64::from) {
+        Some(n) if n > 0 => {
+          let echo = args.get_function(2).unwrap();
+          let mut args = args.clone();
+          args.insert_integer(1, n - 1);
+          engine.call(echo, args)
+        }
+        _ => Ok(LuaTable::default()),
+      }
+    }),
+  );
+  instance.register_library("probe", std::iter::once(("twice", LuaString::from("probe-twice"))).collect()).unwrap();
+
+  let echo = instance.call_string("echo", "").unwrap().get_function(1).unwrap();
+  let mut args = LuaTable::default();
+  args.insert_integer(1, 2);
+  args.insert_chunk(2, echo.clone());
+  args.insert_string(3, "\"q\"\n\u{0416}");
+  args.insert_float(4, 0.25);
+  let values = instance.call(echo, args).unwrap();
+  assert_eq!(format!("{}", values), r#"{[1]="echo",[2]="echo",[3]="echo"}"#);
+  assert_eq!(
+    *seen.lock().unwrap(),
+    vec![
+      r#"{[1]=2,[2]=chunks[2],[3]="\"q\"\n\208\150",[4]=0.25}"#,
+      r#"{[1]=1,[2]=chunks[2],[3]="\"q\"\n\208\150",[4]=0.25}"#,
+      r#"{[1]=0,[2]=chunks[2],[3]="\"q\"\n\208\150",[4]=0.25}"#,
+    ]
+  );
+
+  match instance.call_string("broken", "") {
+    Err(m::ScribuntoError::Lua(e)) => assert_eq!(e.to_string(), "broken!"),
+    _ => panic!("lua error expected"),
+  }
+  assert_eq!(instance.live_chunks(), 3);
+  instance.release(&[LuaChunk::from(1), LuaChunk::from(3)]).unwrap();
+  assert_eq!(instance.live_chunks(), 1);
+
+  // a failing or missing callback is an error raised in Lua, which gets a reply either way
+  instance.insert_callback("probe-fail", Box::new(|_, _| Err(m::ScribuntoError::ModuleNotFound("x".to_owned()))));
+  let names = [("fail", "probe-fail"), ("gone", "probe-gone")];
+  instance.register_library("probe", names.iter().map(|(k, v)| (*k, LuaString::from(*v))).collect()).unwrap();
+  let caller = instance.call_string("caller", "").unwrap().get_function(1).unwrap();
+  let out = instance.call(caller.clone(), std::iter::once((1, LuaString::from("fail"))).collect()).unwrap();
+  assert_eq!(out.get_string(1), Some("module not found: x".into()));
+  let out = instance.call(caller, std::iter::once((1, LuaString::from("gone"))).collect());
+  assert!(matches!(out, Err(m::ScribuntoError::NoSuchFunction(id)) if id == "probe-gone"));
+  assert!(instance.get_status().unwrap().pid > 0);
+  instance.quit().unwrap();
+}
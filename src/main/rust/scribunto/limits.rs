@@ -0,0 +1,133 @@
+#[allow(unused)]
+use crate::scribunto as m;
+use m::Limit;
+
+use std::{
+  process::Child,
+  sync::{
+    mpsc::{self, RecvTimeoutError, Sender},
+    Arc, Mutex,
+  },
+  thread::JoinHandle,
+  time::{Duration, Instant},
+};
+
+// `/proc/<pid>/stat` counts time in USER_HZ, which is fixed for userspace
+const TICKS_PER_SECOND: u64 = 100;
+const POLL: Duration = Duration::from_millis(20);
+
+/// Resource limits of a single top-level call into the interpreter. Defaults follow Scribunto:
+/// 10 seconds and 50 MB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+  pub wall_time: Option<Duration>,
+  pub cpu_time: Option<Duration>,
+  pub memory: Option<u64>,
+}
+impl Default for Limits {
+  fn default() -> Self {
+    Self {
+      wall_time: Some(Duration::from_secs(10)),
+      cpu_time: Some(Duration::from_secs(10)),
+      memory: Some(50 * 1024 * 1024),
+    }
+  }
+}
+impl Limits {
+  pub fn unlimited() -> Self {
+    Self { wall_time: None, cpu_time: None, memory: None }
+  }
+}
+
+struct Usage {
+  cpu_time: Duration,
+  memory: u64,
+}
+impl Usage {
+  fn of(pid: u32) -> Option<Self> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // the command name may contain spaces, the fields are counted after it
+    let fields: Vec<&str> = stat.get(stat.rfind(')')? + 2..)?.split(' ').collect();
+    let field = |id: usize| fields.get(id - 3).and_then(|it| it.parse::<u64>().ok());
+    let ticks = field(14)? + field(15)? + field(16)? + field(17)?;
+    Some(Self { cpu_time: Duration::from_millis(ticks * 1000 / TICKS_PER_SECOND), memory: field(23)? })
+  }
+}
+
+/// Watches the interpreter process while a call is running and kills it as soon as a limit is hit.
+pub struct Watchdog {
+  stop: Sender<()>,
+  thread: JoinHandle<Option<Limit>>,
+}
+impl Watchdog {
+  pub fn arm(process: Arc<Mutex<Child>>, limits: Limits) -> Self {
+    let (stop, stopped) = mpsc::channel();
+    let thread = std::thread::spawn(move || {
+      let pid = process.lock().ok()?.id();
+      let start = Instant::now();
+      let cpu_start = Usage::of(pid).map(|it| it.cpu_time).unwrap_or_default();
+      loop {
+        match stopped.recv_timeout(POLL) {
+          Err(RecvTimeoutError::Timeout) => {}
+          _ => return None,
+        }
+        let usage = Usage::of(pid);
+        let wall = limits.wall_time.filter(|it| start.elapsed() > *it).map(|_| Limit::WallTime);
+        let cpu = limits
+          .cpu_time
+          .zip(usage.as_ref())
+          .filter(|(limit, usage)| usage.cpu_time.saturating_sub(cpu_start) > *limit)
+          .map(|_| Limit::CpuTime);
+        let memory =
+          limits.memory.zip(usage.as_ref()).filter(|(limit, usage)| usage.memory > *limit).map(|_| Limit::Memory);
+        if let Some(limit) = wall.or(cpu).or(memory) {
+          if let Ok(mut process) = process.lock() {
+            let _ = process.kill().map_err(|e| log::warn!("can't kill {}: {}", pid, e));
+            let _ = process.wait();
+          }
+          return Some(limit);
+        }
+      }
+    });
+    Self { stop, thread }
+  }
+  /// Stops watching; tells which limit was hit if the process has been killed.
+  pub fn disarm(self) -> Option<Limit> {
+    let _ = self.stop.send(());
+    self.thread.join().ok().flatten()
+  }
+}
+
+#[cfg(test)]
+#[test]
+fn test_watchdog() {
+  let spawn = |script: &str| {
+    let child = std::process::Command::new("sh").arg("-c").arg(script).spawn().unwrap();
+    Arc::new(Mutex::new(child))
+  };
+  let wait = |process: &Arc<Mutex<Child>>| process.lock().unwrap().try_wait().unwrap();
+  // however busy the machine is, the process is killed long before that
+  let killed = |process: &Arc<Mutex<Child>>, limits: Limits| {
+    let watchdog = Watchdog::arm(process.clone(), limits);
+    let deadline = Instant::now() + Duration::from_secs(20);
+    while wait(process).is_none() && Instant::now() < deadline {
+      std::thread::sleep(POLL);
+    }
+    watchdog.disarm()
+  };
+
+  let limits = Limits { wall_time: Some(Duration::from_millis(100)), ..Limits::unlimited() };
+  assert_eq!(killed(&spawn("sleep 30"), limits), Some(Limit::WallTime));
+  let limits = Limits { cpu_time: Some(Duration::from_millis(100)), ..Limits::unlimited() };
+  assert_eq!(killed(&spawn("while :; do :; done"), limits), Some(Limit::CpuTime));
+  let limits = Limits { memory: Some(1024), ..Limits::unlimited() };
+  assert_eq!(killed(&spawn("sleep 30"), limits), Some(Limit::Memory));
+
+  let process = spawn("sleep 5");
+  let watchdog = Watchdog::arm(process.clone(), Limits::default());
+  assert_eq!(watchdog.disarm(), None);
+  assert!(wait(&process).is_none());
+  let mut process = process.lock().unwrap();
+  let _ = process.kill();
+  let _ = process.wait();
+}
@@ -0,0 +1,106 @@
+#[allow(unused)]
+use crate::scribunto as m;
+use m::{AnyLua, LuaBool, LuaChunk, LuaFloat, LuaInteger, LuaNull, LuaString, LuaTable};
+
+/// Reads back the literals `LuaSender` writes. It is not a Lua parser: only tables of plain values
+/// and `chunks[id]` references are understood.
+pub struct Literal<'a>(&'a [u8]);
+impl Literal<'_> {
+  pub fn table(src: &[u8]) -> Option<LuaTable> {
+    let mut literal = Literal(src);
+    match literal.value()? {
+      AnyLua::Table(table) if literal.0.is_empty() => Some(table),
+      _ => None,
+    }
+  }
+  fn eat(&mut self, prefix: &[u8]) -> bool {
+    let found = self.0.starts_with(prefix);
+    if found {
+      self.0 = &self.0[prefix.len()..];
+    }
+    found
+  }
+  fn value(&mut self) -> Option<AnyLua> {
+    let value: AnyLua = match self.0.first()? {
+      b'{' => {
+        self.eat(b"{");
+        let mut table = LuaTable::default();
+        while !self.eat(b"}") {
+          self.eat(b",");
+          if !self.eat(b"[") {
+            return None;
+          }
+          let key = self.value()?;
+          if !self.eat(b"]=") {
+            return None;
+          }
+          let value = self.value()?;
+          match key {
+            AnyLua::Integer(key) => table.insert(key, value),
+            AnyLua::Float(key) => table.insert(key, value),
+            AnyLua::Bool(key) => table.insert(key, value),
+            AnyLua::String(key) => table.insert(key, value),
+            _ => return None,
+          }
+        }
+        table.into()
+      }
+      b'"' => self.string()?.into(),
+      _ if self.eat(b"true") => LuaBool::from(true).into(),
+      _ if self.eat(b"false") => LuaBool::from(false).into(),
+      _ if self.eat(b"null") || self.eat(b"nil") => LuaNull::default().into(),
+      _ if self.eat(b"(0/0)") => LuaFloat::from(f64::NAN).into(),
+      _ if self.eat(b"(1/0)") => LuaFloat::from(f64::INFINITY).into(),
+      _ if self.eat(b"(-1/0)") => LuaFloat::from(f64::NEG_INFINITY).into(),
+      _ if self.eat(b"chunks[") => {
+        let id: i64 = self.number()?.parse().ok()?;
+        if !self.eat(b"]") {
+          return None;
+        }
+        LuaChunk::from(id).into()
+      }
+      _ => {
+        let number = self.number()?;
+        match number.parse::<i64>() {
+          Ok(it) => LuaInteger::from(it).into(),
+          Err(_) => LuaFloat::from(number.parse::<f64>().ok()?).into(),
+        }
+      }
+    };
+    Some(value)
+  }
+  fn number(&mut self) -> Option<String> {
+    let len = self.0.iter().take_while(|c| c.is_ascii_digit() || matches!(c, b'-' | b'+' | b'.' | b'e')).count();
+    let (number, tail) = self.0.split_at(len);
+    self.0 = tail;
+    Some(std::str::from_utf8(number).ok()?.to_owned()).filter(|it| !it.is_empty())
+  }
+  fn string(&mut self) -> Option<LuaString> {
+    self.eat(b"\"");
+    let mut out = Vec::new();
+    loop {
+      let (&c, tail) = self.0.split_first()?;
+      self.0 = tail;
+      match c {
+        b'"' => return Some(out.into()),
+        b'\\' => {
+          let (&c, tail) = self.0.split_first()?;
+          self.0 = tail;
+          match c {
+            b'n' => out.push(b'\n'),
+            b'r' => out.push(b'\r'),
+            b't' => out.push(b'\t'),
+            c if c.is_ascii_digit() => {
+              let len = 1 + self.0.iter().take(2).take_while(|c| c.is_ascii_digit()).count();
+              let code = std::str::from_utf8(&tail[..len - 1]).ok()?;
+              out.push(format!("{}{}", c as char, code).parse().ok()?);
+              self.0 = &self.0[len - 1..];
+            }
+            c => out.push(c),
+          }
+        }
+        c => out.push(c),
+      }
+    }
+  }
+}
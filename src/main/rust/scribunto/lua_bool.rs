@@ -1,6 +1,6 @@
 #[allow(unused)]
 use crate::scribunto as m;
-use m::{php_error::PhpError, LuaType, Parser};
+use m::{php_error::PhpError, Parser};
 
 use nom::IResult;
 use std::fmt::Display;
@@ -8,12 +8,12 @@ use std::fmt::Display;
 #[derive(PartialEq, Eq, Default, Debug, Clone)]
 pub struct LuaBool(bool);
 impl LuaBool {
-  pub fn parse(src: &str) -> IResult<&str, Self, PhpError<&str>> {
+  pub fn parse(src: &[u8]) -> IResult<&[u8], Self, PhpError<&[u8]>> {
     let (src, prefix) = Parser::prefix(src)?;
-    if prefix != "b" {
-      return Err(PhpError::UnexpectedPrefix("b", prefix.to_string()).into());
+    if prefix != b"b" {
+      return Err(PhpError::UnexpectedPrefix("b", String::from_utf8_lossy(prefix).into_owned()).into());
     }
-    let (src, val) = Parser::i32_val(src)?;
+    let (src, val) = Parser::i64_val(src)?;
     let (src, _) = Parser::finite(src)?;
     Ok((src, Self::from(val != 0)))
   }
@@ -29,5 +29,4 @@ impl Display for LuaBool {
     write!(f, "{}", self.0)
   }
 }
-impl LuaType for LuaBool {}
 crate::transparent_lua!(LuaBool, bool);
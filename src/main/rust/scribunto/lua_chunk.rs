@@ -1,19 +1,19 @@
 #[allow(unused)]
 use crate::scribunto as m;
-use m::{LuaInteger, LuaType};
+use m::LuaInteger;
 
 use std::fmt::Display;
 
 #[derive(PartialEq, Eq, Hash, Default, Debug, Clone)]
-pub struct LuaChunk(i32);
+pub struct LuaChunk(i64);
 impl LuaChunk {
   pub fn new(src: &LuaInteger) -> Self {
     Self(*src.as_raw())
   }
-  pub fn to_raw(self) -> i32 {
+  pub fn to_raw(self) -> i64 {
     self.0
   }
-  pub fn as_raw(&self) -> &i32 {
+  pub fn as_raw(&self) -> &i64 {
     &self.0
   }
   pub fn to_integer(self) -> LuaInteger {
@@ -25,5 +25,4 @@ impl Display for LuaChunk {
     write!(f, "chunks[{}]", self.0)
   }
 }
-impl LuaType for LuaChunk {}
-crate::transparent_lua!(LuaChunk, i32);
+crate::transparent_lua!(LuaChunk, i64);
@@ -1,41 +1,40 @@
 #[allow(unused)]
 use crate::scribunto as m;
-use m::{php_error::PhpError, LuaType, Parser};
+use m::{php_error::PhpError, Parser};
 
 use nom::IResult;
 use std::fmt::Display;
 
 #[derive(Default, Debug, Clone)]
-pub struct LuaFloat(f32);
+pub struct LuaFloat(f64);
 impl LuaFloat {
-  pub fn parse(src: &str) -> IResult<&str, Self, PhpError<&str>> {
+  pub fn parse(src: &[u8]) -> IResult<&[u8], Self, PhpError<&[u8]>> {
     let (src, prefix) = Parser::prefix(src)?;
-    if prefix != "d" {
-      return Err(PhpError::UnexpectedPrefix("d", prefix.to_string()).into());
+    if prefix != b"d" {
+      return Err(PhpError::UnexpectedPrefix("d", String::from_utf8_lossy(prefix).into_owned()).into());
     }
-    let (src, val) = Parser::f32_val(src)?;
+    let (src, val) = Parser::f64_val(src)?;
     let (src, _) = Parser::finite(src)?;
     Ok((src, Self::from(val)))
   }
-  pub fn to_raw(self) -> f32 {
+  pub fn to_raw(self) -> f64 {
     self.0
   }
-  pub fn as_raw(&self) -> &f32 {
+  pub fn as_raw(&self) -> &f64 {
     &self.0
   }
 }
 impl Display for LuaFloat {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    // `nan` and `inf` are plain (nil) globals in Lua, so spell them as expressions
     if self.0.is_nan() {
-      let sign = if self.0.is_sign_negative() { "-" } else { "" };
-      write!(f, "{}nan", sign)
+      f.write_str("(0/0)")
     } else if self.0.is_infinite() {
       let sign = if self.0.is_sign_negative() { "-" } else { "" };
-      write!(f, "{}inf", sign)
+      write!(f, "({}1/0)", sign)
     } else {
       write!(f, "{}", self.0)
     }
   }
 }
-impl LuaType for LuaFloat {}
-crate::transparent_lua!(LuaFloat, f32);
+crate::transparent_lua!(LuaFloat, f64);
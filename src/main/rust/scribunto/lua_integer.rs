@@ -1,26 +1,26 @@
 #[allow(unused)]
 use crate::scribunto as m;
-use m::{lua_chunk::LuaChunk, php_error::PhpError, LuaNameType, LuaString, LuaType, Parser};
+use m::{lua_chunk::LuaChunk, php_error::PhpError, Parser};
 
 use nom::IResult;
 use std::fmt::Display;
 
 #[derive(PartialEq, Eq, Hash, Default, Debug, Clone)]
-pub struct LuaInteger(i32);
+pub struct LuaInteger(i64);
 impl LuaInteger {
-  pub fn parse(src: &str) -> IResult<&str, Self, PhpError<&str>> {
+  pub fn parse(src: &[u8]) -> IResult<&[u8], Self, PhpError<&[u8]>> {
     let (src, prefix) = Parser::prefix(src)?;
-    if prefix != "i" {
-      return Err(PhpError::UnexpectedPrefix("i", prefix.to_string()).into());
+    if prefix != b"i" {
+      return Err(PhpError::UnexpectedPrefix("i", String::from_utf8_lossy(prefix).into_owned()).into());
     }
-    let (src, val) = Parser::i32_val(src)?;
+    let (src, val) = Parser::i64_val(src)?;
     let (src, _) = Parser::finite(src)?;
     Ok((src, Self::from(val)))
   }
-  pub fn to_raw(self) -> i32 {
+  pub fn to_raw(self) -> i64 {
     self.0
   }
-  pub fn as_raw(&self) -> &i32 {
+  pub fn as_raw(&self) -> &i64 {
     &self.0
   }
   pub fn to_chunk(self) -> LuaChunk {
@@ -32,18 +32,4 @@ impl Display for LuaInteger {
     write!(f, "{}", self.0)
   }
 }
-impl LuaType for LuaInteger {}
-crate::transparent_lua!(LuaInteger, i32);
-impl LuaNameType for LuaInteger {
-  fn try_from_string(src: LuaString) -> Result<Box<Self>, LuaString> {
-    let s: String = src.to_raw();
-    if let Ok(i) = s.parse::<i32>() {
-      Ok(Box::new(Self::from(i)))
-    } else {
-      Err(s.into())
-    }
-  }
-  fn try_from_integer(src: LuaInteger) -> Result<Box<Self>, LuaInteger> {
-    Ok(Box::new(src))
-  }
-}
+crate::transparent_lua!(LuaInteger, i64);
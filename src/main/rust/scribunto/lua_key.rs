@@ -0,0 +1,133 @@
+#[allow(unused)]
+use crate::scribunto as m;
+use m::{LuaBool, LuaFloat, LuaInteger, LuaString};
+
+use std::{cmp::Ordering, fmt::Display};
+
+/// Table key. The ordering puts the array part first: integers ascending, then floats, booleans and strings.
+#[derive(Debug, Clone)]
+pub enum LuaKey {
+  Integer(i64),
+  Float(f64),
+  Bool(bool),
+  String(LuaString),
+}
+impl LuaKey {
+  fn rank(&self) -> u8 {
+    match self {
+      Self::Integer(_) => 0,
+      Self::Float(_) => 1,
+      Self::Bool(_) => 2,
+      Self::String(_) => 3,
+    }
+  }
+  pub fn as_integer(&self) -> Option<i64> {
+    match self {
+      Self::Integer(i) => Some(*i),
+      _ => None,
+    }
+  }
+  pub fn as_string(&self) -> Option<&LuaString> {
+    match self {
+      Self::String(s) => Some(s),
+      _ => None,
+    }
+  }
+}
+impl PartialEq for LuaKey {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+impl Eq for LuaKey {}
+impl PartialOrd for LuaKey {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+impl Ord for LuaKey {
+  fn cmp(&self, other: &Self) -> Ordering {
+    match (self, other) {
+      (Self::Integer(a), Self::Integer(b)) => a.cmp(b),
+      (Self::Float(a), Self::Float(b)) => a.total_cmp(b),
+      (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+      (Self::String(a), Self::String(b)) => a.as_raw().cmp(b.as_raw()),
+      (a, b) => a.rank().cmp(&b.rank()),
+    }
+  }
+}
+impl Display for LuaKey {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Integer(v) => write!(f, "{}", v),
+      Self::Float(v) => LuaFloat::from(*v).fmt(f),
+      Self::Bool(v) => write!(f, "{}", v),
+      Self::String(v) => v.fmt(f),
+    }
+  }
+}
+impl From<i64> for LuaKey {
+  fn from(src: i64) -> Self {
+    Self::Integer(src)
+  }
+}
+impl From<i32> for LuaKey {
+  fn from(src: i32) -> Self {
+    Self::Integer(src.into())
+  }
+}
+impl From<usize> for LuaKey {
+  fn from(src: usize) -> Self {
+    Self::Integer(src as _)
+  }
+}
+impl From<f64> for LuaKey {
+  // Lua normalises integral float keys, `t[2.0]` is `t[2]`
+  fn from(src: f64) -> Self {
+    if src.fract() == 0.0 && src >= i64::MIN as f64 && src < i64::MAX as f64 {
+      Self::Integer(src as _)
+    } else {
+      Self::Float(src)
+    }
+  }
+}
+impl From<bool> for LuaKey {
+  fn from(src: bool) -> Self {
+    Self::Bool(src)
+  }
+}
+impl From<&str> for LuaKey {
+  fn from(src: &str) -> Self {
+    Self::String(src.into())
+  }
+}
+impl From<String> for LuaKey {
+  fn from(src: String) -> Self {
+    Self::String(src.into())
+  }
+}
+impl From<&String> for LuaKey {
+  fn from(src: &String) -> Self {
+    Self::String(src.into())
+  }
+}
+impl From<LuaString> for LuaKey {
+  fn from(src: LuaString) -> Self {
+    Self::String(src)
+  }
+}
+impl From<LuaInteger> for LuaKey {
+  fn from(src: LuaInteger) -> Self {
+    Self::Integer(src.into())
+  }
+}
+impl From<LuaFloat> for LuaKey {
+  fn from(src: LuaFloat) -> Self {
+    f64::from(src).into()
+  }
+}
+impl From<LuaBool> for LuaKey {
+  fn from(src: LuaBool) -> Self {
+    Self::Bool(src.into())
+  }
+}
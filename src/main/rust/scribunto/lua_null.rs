@@ -1,6 +1,6 @@
 #[allow(unused)]
 use crate::scribunto as m;
-use m::{php_error::PhpError, LuaType, Parser};
+use m::{php_error::PhpError, Parser};
 
 use nom::IResult;
 use std::fmt::Display;
@@ -8,7 +8,7 @@ use std::fmt::Display;
 #[derive(Default, Debug, Clone)]
 pub struct LuaNull(());
 impl LuaNull {
-  pub fn parse(src: &str) -> IResult<&str, Self, PhpError<&str>> {
+  pub fn parse(src: &[u8]) -> IResult<&[u8], Self, PhpError<&[u8]>> {
     let (src, _) = Parser::null_val(src)?;
     let (src, _) = Parser::finite(src)?;
     Ok((src, Self(())))
@@ -25,5 +25,4 @@ impl Display for LuaNull {
     f.write_str("null")
   }
 }
-impl LuaType for LuaNull {}
 crate::transparent_lua!(LuaNull, ());
@@ -1,17 +1,18 @@
 #[allow(unused)]
 use crate::scribunto as m;
-use m::{php_error::PhpError, LuaInteger, LuaNameType, LuaType, Parser};
+use m::{php_error::PhpError, Parser};
 
 use nom::IResult;
-use std::fmt::Display;
+use std::{borrow::Cow, fmt::Display};
 
+/// Lua strings are byte strings: they are neither required to be valid UTF-8 nor free of NULs.
 #[derive(PartialEq, Eq, Hash, Default, Debug, Clone)]
-pub struct LuaString(String);
+pub struct LuaString(Vec<u8>);
 impl LuaString {
-  pub fn parse(src: &str) -> IResult<&str, Self, PhpError<&str>> {
+  pub fn parse(src: &[u8]) -> IResult<&[u8], Self, PhpError<&[u8]>> {
     let (src, prefix) = Parser::prefix(src)?;
-    if prefix != "s" {
-      return Err(PhpError::UnexpectedPrefix("s", prefix.to_string()).into());
+    if prefix != b"s" {
+      return Err(PhpError::UnexpectedPrefix("s", String::from_utf8_lossy(prefix).into_owned()).into());
     }
     let (src, ch_len) = Parser::usize_val(src)?;
     let (src, _) = Parser::separator(src)?;
@@ -19,35 +20,65 @@ impl LuaString {
     let (src, _) = Parser::finite(src)?;
     Ok((src, Self::from(val)))
   }
-  pub fn to_raw(self) -> String {
+  pub fn to_raw(self) -> Vec<u8> {
     self.0
   }
-  pub fn as_raw(&self) -> &str {
-    self.0.as_str()
+  pub fn as_raw(&self) -> &[u8] {
+    self.0.as_slice()
+  }
+  pub fn as_str(&self) -> Option<&str> {
+    std::str::from_utf8(&self.0).ok()
+  }
+  pub fn to_string_lossy(&self) -> Cow<'_, str> {
+    String::from_utf8_lossy(&self.0)
   }
 }
-impl<T: Into<String>> From<T> for LuaString {
-  fn from(src: T) -> Self {
-    let src: String = src.into();
+impl From<Vec<u8>> for LuaString {
+  fn from(src: Vec<u8>) -> Self {
     Self(src)
   }
 }
-impl AsRef<str> for LuaString {
-  fn as_ref(&self) -> &str {
-    self.0.as_ref()
+impl From<&[u8]> for LuaString {
+  fn from(src: &[u8]) -> Self {
+    Self(src.to_owned())
   }
 }
-impl Display for LuaString {
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    write!(f, r#""{}""#, self.0)
+impl From<String> for LuaString {
+  fn from(src: String) -> Self {
+    Self(src.into_bytes())
+  }
+}
+impl From<&String> for LuaString {
+  fn from(src: &String) -> Self {
+    Self(src.as_bytes().to_owned())
   }
 }
-impl LuaType for LuaString {}
-impl LuaNameType for LuaString {
-  fn try_from_string(src: LuaString) -> Result<Box<Self>, LuaString> {
-    Ok(Box::new(src))
+impl From<&str> for LuaString {
+  fn from(src: &str) -> Self {
+    Self(src.as_bytes().to_owned())
   }
-  fn try_from_integer(src: LuaInteger) -> Result<Box<Self>, LuaInteger> {
-    Ok(Box::new(Self::from(format!("{}", i32::from(src)))))
+}
+impl AsRef<[u8]> for LuaString {
+  fn as_ref(&self) -> &[u8] {
+    self.0.as_ref()
+  }
+}
+impl Display for LuaString {
+  // the literal is pure ASCII: everything outside of printable ASCII goes as a decimal escape,
+  // so MWServer's `loadstring` restores the exact bytes whatever encoding they are in
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str("\"")?;
+    for &c in self.0.iter() {
+      match c {
+        b'"' => f.write_str("\\\"")?,
+        b'\\' => f.write_str("\\\\")?,
+        b'\n' => f.write_str("\\n")?,
+        b'\r' => f.write_str("\\r")?,
+        b'\t' => f.write_str("\\t")?,
+        0x20..=0x7e => write!(f, "{}", c as char)?,
+        c => write!(f, "\\{:03}", c)?,
+      }
+    }
+    f.write_str("\"")
   }
 }
@@ -1,13 +1,13 @@
 #[allow(unused)]
 use crate::scribunto as m;
 use m::{
-  lua_bool::LuaBool, lua_chunk::LuaChunk, lua_null::LuaNull, php_error::PhpError, LuaFloat, LuaInteger, LuaNameType,
-  LuaString, LuaType, Parser,
+  lua_bool::LuaBool, lua_chunk::LuaChunk, lua_key::LuaKey, lua_null::LuaNull, php_error::PhpError, LuaFloat,
+  LuaInteger, LuaString, Parser,
 };
 
 use jnino::JFace;
 use nom::IResult;
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::BTreeMap, fmt::Display};
 
 macro_rules! any {
   ($raw: ty, $kind: expr, $pkind: path, $asraw: ident) => {
@@ -35,8 +35,7 @@ pub enum AnyLua {
   Bool(LuaBool),
   Integer(LuaInteger),
   Chunk(LuaChunk),
-  StringTable(LuaTable<LuaString>),
-  IntegerTable(LuaTable<LuaInteger>),
+  Table(LuaTable),
 }
 impl std::fmt::Display for AnyLua {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -47,8 +46,7 @@ impl std::fmt::Display for AnyLua {
       AnyLua::Bool(v) => v.fmt(f),
       AnyLua::Integer(v) => v.fmt(f),
       AnyLua::Chunk(v) => v.fmt(f),
-      AnyLua::StringTable(v) => v.fmt(f),
-      AnyLua::IntegerTable(v) => v.fmt(f),
+      AnyLua::Table(v) => v.fmt(f),
     }
   }
 }
@@ -58,98 +56,117 @@ any!(LuaNull, AnyLua::Null, AnyLua::Null, as_null);
 any!(LuaBool, AnyLua::Bool, AnyLua::Bool, as_bool);
 any!(LuaInteger, AnyLua::Integer, AnyLua::Integer, as_integer);
 any!(LuaChunk, AnyLua::Chunk, AnyLua::Chunk, to_chunk);
-any!(LuaTable<LuaInteger>, AnyLua::IntegerTable, AnyLua::IntegerTable, as_integer_table);
-
-impl From<LuaTable<LuaString>> for AnyLua {
-  fn from(me: LuaTable<LuaString>) -> Self {
-    AnyLua::StringTable(me)
-  }
-}
-impl AnyLua {
-  pub fn as_string_table(&self) -> Option<LuaTable<LuaString>> {
-    match &self {
-      AnyLua::StringTable(me) => Some(me.clone()),
-      AnyLua::IntegerTable(me) => Some(me.into()),
-      _ => None,
-    }
-  }
-}
+any!(LuaTable, AnyLua::Table, AnyLua::Table, as_table);
 
+/// Lua table with keys of any scalar type. Entries are kept ordered by key, so the array part
+/// comes first and a table always serialises the same way.
 #[derive(Default, Debug, Clone, JFace)]
-pub struct LuaTable<T: LuaNameType> {
-  pub value: HashMap<T, Box<AnyLua>>,
+pub struct LuaTable {
+  pub value: BTreeMap<LuaKey, AnyLua>,
   pub object: Option<String>,
 }
-impl<T: LuaNameType> AsMut<HashMap<T, Box<AnyLua>>> for LuaTable<T> {
-  fn as_mut(&mut self) -> &mut HashMap<T, Box<AnyLua>> {
+impl AsMut<BTreeMap<LuaKey, AnyLua>> for LuaTable {
+  fn as_mut(&mut self) -> &mut BTreeMap<LuaKey, AnyLua> {
     &mut self.value
   }
 }
-impl<T: LuaNameType> AsRef<HashMap<T, Box<AnyLua>>> for LuaTable<T> {
-  fn as_ref(&self) -> &HashMap<T, Box<AnyLua>> {
+impl AsRef<BTreeMap<LuaKey, AnyLua>> for LuaTable {
+  fn as_ref(&self) -> &BTreeMap<LuaKey, AnyLua> {
     &self.value
   }
 }
-impl<T: LuaNameType> LuaTable<T> {
+impl LuaTable {
   pub fn len(&self) -> usize {
     self.value.len()
   }
-  pub fn insert<A: Into<T>, B: Into<AnyLua>>(&mut self, property: A, value: B) {
-    self.as_mut().insert(property.into(), Box::new(value.into()));
+  pub fn is_empty(&self) -> bool {
+    self.value.is_empty()
   }
-  pub fn insert_string<A: Into<T>, B: Into<LuaString>>(&mut self, property: A, value: B) {
-    self.insert(property.into(), value.into());
+  /// The greatest positive integer key: what `select('#', ...)` gives for an argument list, holes included.
+  pub fn sequence_len(&self) -> usize {
+    self.value.keys().rev().find_map(LuaKey::as_integer).filter(|it| *it > 0).unwrap_or_default() as _
   }
-  pub fn insert_bool<A: Into<T>, B: Into<LuaBool>>(&mut self, property: A, value: B) {
-    self.insert(property.into(), value.into());
+  pub fn push<B: Into<AnyLua>>(&mut self, value: B) {
+    let id = self.sequence_len() + 1;
+    self.insert(id, value);
   }
-  pub fn insert_null<A: Into<T>, B: Into<LuaNull>>(&mut self, property: A, value: B) {
-    self.insert(property.into(), value.into());
+  pub fn insert<A: Into<LuaKey>, B: Into<AnyLua>>(&mut self, property: A, value: B) {
+    self.as_mut().insert(property.into(), value.into());
   }
-  pub fn insert_integer<A: Into<T>, B: Into<LuaInteger>>(&mut self, property: A, value: B) {
-    self.insert(property.into(), value.into());
+  pub fn insert_string<A: Into<LuaKey>, B: Into<LuaString>>(&mut self, property: A, value: B) {
+    self.insert(property, value.into());
   }
-  pub fn insert_chunk<A: Into<T>, B: Into<LuaChunk>>(&mut self, property: A, value: B) {
-    self.insert(property.into(), value.into());
+  pub fn insert_bool<A: Into<LuaKey>, B: Into<LuaBool>>(&mut self, property: A, value: B) {
+    self.insert(property, value.into());
   }
-  pub fn insert_float<A: Into<T>, B: Into<LuaFloat>>(&mut self, property: A, value: B) {
-    self.insert(property.into(), value.into());
+  pub fn insert_null<A: Into<LuaKey>, B: Into<LuaNull>>(&mut self, property: A, value: B) {
+    self.insert(property, value.into());
   }
-  pub fn insert_string_table<A: Into<T>, B: Into<LuaTable<LuaString>>>(&mut self, property: A, value: B) {
-    self.insert(property.into(), value.into());
+  pub fn insert_integer<A: Into<LuaKey>, B: Into<LuaInteger>>(&mut self, property: A, value: B) {
+    self.insert(property, value.into());
   }
-  pub fn insert_integer_table<A: Into<T>, B: Into<LuaTable<LuaInteger>>>(&mut self, property: A, value: B) {
-    self.insert(property.into(), value.into());
+  pub fn insert_chunk<A: Into<LuaKey>, B: Into<LuaChunk>>(&mut self, property: A, value: B) {
+    self.insert(property, value.into());
   }
-  fn get<A: Into<T>>(&self, property: A) -> Option<&AnyLua> {
-    self.value.get(&property.into()).map(Box::as_ref)
+  pub fn insert_float<A: Into<LuaKey>, B: Into<LuaFloat>>(&mut self, property: A, value: B) {
+    self.insert(property, value.into());
   }
-  pub fn get_string<A: Into<T>>(&self, property: A) -> Option<LuaString> {
+  pub fn insert_table<A: Into<LuaKey>, B: Into<LuaTable>>(&mut self, property: A, value: B) {
+    self.insert(property, value.into());
+  }
+  pub fn get<A: Into<LuaKey>>(&self, property: A) -> Option<&AnyLua> {
+    self.value.get(&property.into())
+  }
+  pub fn get_string<A: Into<LuaKey>>(&self, property: A) -> Option<LuaString> {
     self.get(property).and_then(AnyLua::as_string)
   }
-  pub fn get_bool<A: Into<T>>(&self, property: A) -> Option<LuaBool> {
+  pub fn get_bool<A: Into<LuaKey>>(&self, property: A) -> Option<LuaBool> {
     self.get(property).and_then(AnyLua::as_bool)
   }
-  pub fn get_null<A: Into<T>>(&self, property: A) -> Option<LuaNull> {
+  pub fn get_null<A: Into<LuaKey>>(&self, property: A) -> Option<LuaNull> {
     self.get(property).and_then(AnyLua::as_null)
   }
-  pub fn get_integer<A: Into<T>>(&self, property: A) -> Option<LuaInteger> {
+  pub fn get_integer<A: Into<LuaKey>>(&self, property: A) -> Option<LuaInteger> {
     self.get(property).and_then(AnyLua::as_integer)
   }
-  pub fn get_float<A: Into<T>>(&self, property: A) -> Option<LuaFloat> {
+  pub fn get_float<A: Into<LuaKey>>(&self, property: A) -> Option<LuaFloat> {
     self.get(property).and_then(AnyLua::as_float)
   }
-  pub fn get_string_table<A: Into<T>>(&self, property: A) -> Option<LuaTable<LuaString>> {
-    self.get(property).and_then(AnyLua::as_string_table)
+  pub fn get_table<A: Into<LuaKey>>(&self, property: A) -> Option<LuaTable> {
+    self.get(property).and_then(AnyLua::as_table)
+  }
+  pub fn get_function<A: Into<LuaKey>>(&self, property: A) -> Option<LuaChunk> {
+    self.get(property).and_then(AnyLua::to_chunk)
+  }
+  pub fn iter(&self) -> impl Iterator<Item = (&LuaKey, &AnyLua)> {
+    self.value.iter()
+  }
+  /// Every function referenced from this table or any table nested in it.
+  pub fn chunks(&self) -> Vec<LuaChunk> {
+    self.value.values().fold(Vec::new(), |mut acc, it| {
+      match it {
+        AnyLua::Chunk(chunk) => acc.push(chunk.clone()),
+        AnyLua::Table(table) => acc.extend(table.chunks()),
+        _ => {}
+      }
+      acc
+    })
   }
-  pub fn get_integer_table<A: Into<T>>(&self, property: A) -> Option<LuaTable<LuaInteger>> {
-    self.get(property).and_then(AnyLua::as_integer_table)
+}
+impl IntoIterator for LuaTable {
+  type Item = (LuaKey, AnyLua);
+  type IntoIter = std::collections::btree_map::IntoIter<LuaKey, AnyLua>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.value.into_iter()
   }
-  pub fn get_function<A: Into<T>>(&self, property: A) -> Option<LuaChunk> {
-    self.get_string_table(property).and_then(|it| it.get_integer("id")).map(|it| it.to_chunk())
+}
+impl<K: Into<LuaKey>, V: Into<AnyLua>> std::iter::FromIterator<(K, V)> for LuaTable {
+  fn from_iter<Q: IntoIterator<Item = (K, V)>>(iter: Q) -> Self {
+    Self { value: iter.into_iter().map(|(k, v)| (k.into(), v.into())).collect(), object: None }
   }
 }
-impl<T: LuaNameType> Display for LuaTable<T> {
+impl Display for LuaTable {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.write_str("{")?;
     let mut i = self.value.iter().peekable();
@@ -162,51 +179,38 @@ impl<T: LuaNameType> Display for LuaTable<T> {
     f.write_str("}")
   }
 }
-impl<T: LuaNameType> LuaType for LuaTable<T> {}
-impl<T: LuaNameType> LuaTable<T> {
-  pub fn into_iter(self) -> impl Iterator<Item = (T, Box<AnyLua>)> {
-    self.value.into_iter()
-  }
-  pub fn parse(src: &str) -> IResult<&str, Self, PhpError<&str>> {
+impl LuaTable {
+  pub fn parse(src: &[u8]) -> IResult<&[u8], Self, PhpError<&[u8]>> {
     let (mut src, prefix) = Parser::prefix(src)?;
     let object = match prefix {
-      "a" => None,
-      "O" => {
+      b"a" => None,
+      b"O" => {
         let (tmp, ch_len) = Parser::usize_val(src)?;
         let (tmp, _) = Parser::separator(tmp)?;
         let (tmp, val) = Parser::str_val(tmp, ch_len)?;
         let (tmp, _) = Parser::separator(tmp)?;
         src = tmp;
-        Some(val)
+        Some(String::from_utf8_lossy(&val).into_owned())
       }
-      s => return Err(PhpError::UnexpectedPrefix("a/O", s.to_string()).into()),
+      s => return Err(PhpError::UnexpectedPrefix("a/O", String::from_utf8_lossy(s).into_owned()).into()),
     };
     let (src, pairs) = Parser::usize_val(src)?;
     let (src, _) = Parser::separator(src)?;
     let (src, _) = Parser::open(src)?;
-    let mut fields = HashMap::new();
+    let mut fields = BTreeMap::new();
     let mut src = src;
     for _ in 0..pairs {
-      if let Ok((tmp, field)) = LuaString::parse(src) {
-        let (tmp, any) = Parser::any_lua(tmp)?;
-        fields.insert(*T::try_from_string(field).map_err(|_| PhpError::BadType)?, Box::new(any));
-        src = tmp;
+      let (tmp, field) = if let Ok((tmp, field)) = LuaString::parse(src) {
+        (tmp, LuaKey::from(field))
       } else {
         let (tmp, field) = LuaInteger::parse(src)?;
-        let (tmp, any) = Parser::any_lua(tmp)?;
-        fields.insert(*T::try_from_integer(field).map_err(|_| PhpError::BadType)?, Box::new(any));
-        src = tmp;
-      }
+        (tmp, LuaKey::from(field))
+      };
+      let (tmp, any) = Parser::any_lua(tmp)?;
+      fields.insert(field, any);
+      src = tmp;
     }
     let (src, _) = Parser::close(src)?;
     Ok((src, Self { value: fields, object }))
   }
 }
-impl From<&LuaTable<LuaInteger>> for LuaTable<LuaString> {
-  fn from(src: &LuaTable<LuaInteger>) -> Self {
-    LuaTable {
-      value: src.value.iter().map(|it| (LuaString::from(format!("{}", it.0.as_raw())), it.1.clone())).collect(),
-      object: src.object.clone(),
-    }
-  }
-}
@@ -0,0 +1,196 @@
+#[allow(unused)]
+use crate::scribunto as m;
+use m::literal::Literal;
+
+use std::{
+  cell::RefCell,
+  collections::{HashMap, VecDeque},
+  fs::File,
+  io::{self, BufWriter, Read, Write},
+  path::Path,
+  rc::Rc,
+};
+
+const TO_LUA: &[u8] = "» ".as_bytes();
+const FROM_LUA: &[u8] = "« ".as_bytes();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+  ToLua,
+  FromLua,
+}
+
+/// Where `LuaSender` and `LuaReceiver` write the session down: a line per message, `» ` and the
+/// literal for what goes to Lua, `« ` and the body as it came over the wire for the rest. Neither
+/// side puts raw newlines in a body, so a line is always a whole message.
+#[derive(Clone)]
+pub struct Recorder(Rc<RefCell<Box<dyn Write>>>);
+impl Recorder {
+  pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    Ok(Self::from_writer(BufWriter::new(File::create(path)?)))
+  }
+  pub fn from_writer<W: Write + 'static>(writer: W) -> Self {
+    Self(Rc::new(RefCell::new(Box::new(writer))))
+  }
+  pub fn record(&self, direction: Direction, body: &[u8]) -> io::Result<()> {
+    let mut writer = self.0.borrow_mut();
+    writer.write_all(match direction {
+      Direction::ToLua => TO_LUA,
+      Direction::FromLua => FROM_LUA,
+    })?;
+    writer.write_all(body)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+  }
+}
+
+/// A recorded session to be served back: Lua's replies come from the recording, and what Rust sends
+/// has to be exactly what was recorded, or the replay stops with an `InvalidData` error.
+pub struct Replay {
+  messages: VecDeque<(Direction, Vec<u8>)>,
+}
+impl Replay {
+  pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    Self::parse(&std::fs::read(path)?)
+  }
+  pub fn parse(src: &[u8]) -> io::Result<Self> {
+    let mut messages = VecDeque::new();
+    for (id, line) in src.split(|c| *c == b'\n').enumerate().filter(|it| !it.1.is_empty()) {
+      let message = if let Some(body) = line.strip_prefix(TO_LUA) {
+        (Direction::ToLua, body.to_owned())
+      } else if let Some(body) = line.strip_prefix(FROM_LUA) {
+        (Direction::FromLua, body.to_owned())
+      } else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("line {}: no direction", id + 1)));
+      };
+      messages.push_back(message);
+    }
+    Ok(Self { messages })
+  }
+  /// Module sources sent with `loadString`, by chunk name.
+  pub fn sources(&self) -> HashMap<String, String> {
+    self
+      .messages
+      .iter()
+      .filter(|it| it.0 == Direction::ToLua)
+      .filter_map(|it| Literal::table(&it.1))
+      .filter(|it| it.get_string("op").map(|op| op.as_raw() == b"loadString").unwrap_or_default())
+      .filter_map(|it| {
+        let name = it.get_string("chunkName")?.to_string_lossy().into_owned();
+        Some((name, it.get_string("text")?.to_string_lossy().into_owned()))
+      })
+      .collect()
+  }
+  pub fn split(self) -> (ReplayReader, ReplayWriter) {
+    let messages = Rc::new(RefCell::new(self.messages));
+    let reader = ReplayReader { messages: messages.clone(), buffer: Vec::new(), position: 0 };
+    (reader, ReplayWriter { messages, pending: Vec::new() })
+  }
+}
+
+type Messages = Rc<RefCell<VecDeque<(Direction, Vec<u8>)>>>;
+
+fn diverged<T: std::fmt::Display>(message: T) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, format!("replay diverged: {}", message))
+}
+
+pub struct ReplayReader {
+  messages: Messages,
+  buffer: Vec<u8>,
+  position: usize,
+}
+impl Read for ReplayReader {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    if self.position == self.buffer.len() {
+      let mut messages = self.messages.borrow_mut();
+      match messages.front() {
+        None => return Ok(0),
+        Some((Direction::ToLua, body)) => {
+          return Err(diverged(format!("waiting for {}", String::from_utf8_lossy(body))));
+        }
+        Some((Direction::FromLua, _)) => {
+          let (_, body) = messages.pop_front().unwrap_or((Direction::FromLua, Vec::new()));
+          self.buffer = format!("{:08x}{:08x}", body.len(), body.len() * 2 - 1).into_bytes();
+          self.buffer.extend_from_slice(&body);
+          self.position = 0;
+        }
+      }
+    }
+    let len = buf.len().min(self.buffer.len() - self.position);
+    buf[..len].copy_from_slice(&self.buffer[self.position..self.position + len]);
+    self.position += len;
+    Ok(len)
+  }
+}
+
+pub struct ReplayWriter {
+  messages: Messages,
+  pending: Vec<u8>,
+}
+impl Write for ReplayWriter {
+  // a message may come in several writes, it is checked once it is whole
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.pending.extend_from_slice(buf);
+    while self.pending.len() >= 16 {
+      let length = std::str::from_utf8(&self.pending[..8]).ok().and_then(|it| usize::from_str_radix(it, 16).ok());
+      let length = length.ok_or_else(|| diverged("bad header"))?;
+      if self.pending.len() < 16 + length {
+        break;
+      }
+      let body: Vec<u8> = self.pending.drain(..16 + length).skip(16).collect();
+      match self.messages.borrow_mut().pop_front() {
+        Some((Direction::ToLua, expected)) if expected == body => {}
+        Some((_, expected)) => {
+          return Err(diverged(format!(
+            "sent {} instead of {}",
+            String::from_utf8_lossy(&body),
+            String::from_utf8_lossy(&expected)
+          )))
+        }
+        None => return Err(diverged(format!("sent {} after the end", String::from_utf8_lossy(&body)))),
+      }
+    }
+    Ok(buf.len())
+  }
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+#[test]
+fn test_replay() {
+  use m::{AnyLua, FakeServer, LuaEngine, LuaInstance, LuaString, LuaTable, ScribuntoError};
+
+  let path = std::env::temp_dir().join(format!("widictor-replay-{}.log", std::process::id()));
+  let session = |instance: &mut dyn LuaEngine| -> Result<Vec<String>, ScribuntoError> {
+    let mut out = Vec::new();
+    let chunk = instance.load_string("shout", "return ...")?;
+    for word in ["a\nb", "\u{0416}\\"].iter() {
+      let args = std::iter::once((1, LuaString::from(*word))).collect();
+      out.push(instance.call(chunk.clone(), args)?.to_string());
+    }
+    Ok(out)
+  };
+  let server = FakeServer::new().module("shout", |_, args: LuaTable| {
+    let word = args.get_string(1).unwrap_or_default().to_string_lossy().to_uppercase();
+    Ok(std::iter::once((1, AnyLua::from(LuaString::from(word)))).collect())
+  });
+  let mut instance = server.spawn(vec![]);
+  instance.record(Recorder::create(&path).unwrap());
+  let recorded = session(&mut instance).unwrap();
+  assert_eq!(recorded, vec![r#"{[1]="A\nB"}"#, r#"{[1]="\208\150\\"}"#]);
+
+  let mut replay = LuaInstance::replay(&path).unwrap();
+  assert_eq!(session(&mut replay).unwrap(), recorded);
+  assert!(matches!(replay.call_string("shout", "return"), Err(ScribuntoError::Io(_))));
+
+  let mut replay = LuaInstance::replay(&path).unwrap();
+  let chunk = replay.load_string("shout", "return ...").unwrap();
+  let args = std::iter::once((1, LuaString::from("c"))).collect();
+  match replay.call(chunk, args) {
+    Err(ScribuntoError::Io(e)) => assert!(e.to_string().starts_with("replay diverged: sent")),
+    _ => panic!("divergence expected"),
+  }
+  let _ = std::fs::remove_file(path);
+}
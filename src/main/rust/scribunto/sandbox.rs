@@ -0,0 +1,107 @@
+#[allow(unused)]
+use crate::scribunto as m;
+use m::LuaTable;
+
+use std::{
+  collections::BTreeSet,
+  path::{Component, Path},
+};
+
+// what Scribunto leaves to modules, plus the libraries the `mw` setup adds
+const GLOBALS: &[&str] = &[
+  "_G",
+  "_VERSION",
+  "assert",
+  "debug",
+  "error",
+  "getfenv",
+  "getmetatable",
+  "ipairs",
+  "math",
+  "mw",
+  "next",
+  "os",
+  "package",
+  "pairs",
+  "pcall",
+  "rawequal",
+  "rawget",
+  "rawset",
+  "require",
+  "select",
+  "setfenv",
+  "setmetatable",
+  "string",
+  "table",
+  "tonumber",
+  "tostring",
+  "type",
+  "unpack",
+  "xpcall",
+];
+
+/// What modules are allowed to reach. Applied by `pkg/sandbox.lua` to the base environment once the
+/// `mw` setup is done, then audited: anything dangerous still in there fails the setup.
+///
+/// `setfenv` and `getfenv` stay in the base environment whatever `env_funcs` says, `mw` needs them;
+/// it is `mw.executeModule` that leaves them out of module environments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sandbox {
+  /// Globals kept in the base environment, every other one is removed.
+  pub globals: BTreeSet<String>,
+  /// Passed to `mw` as `allowEnvFuncs`.
+  pub env_funcs: bool,
+  /// Keeps `loadstring`, confined to the environment of its caller.
+  pub loadstring: bool,
+  /// Lets modules be loaded by any path. `io`, `dofile`, `os.execute` and the like are also left in the
+  /// interpreter's globals, but modules never see them: `MWServer:newEnvironment` does not copy them.
+  pub filesystem: bool,
+}
+impl Default for Sandbox {
+  fn default() -> Self {
+    Self {
+      globals: GLOBALS.iter().map(|it| it.to_string()).collect(),
+      env_funcs: false,
+      loadstring: false,
+      filesystem: false,
+    }
+  }
+}
+impl Sandbox {
+  /// Options for the function `pkg/sandbox.lua` returns.
+  pub fn options(&self) -> LuaTable {
+    let mut globals = LuaTable::default();
+    self.globals.iter().for_each(|it| globals.insert_bool(it.as_str(), true));
+    if self.loadstring {
+      globals.insert_bool("loadstring", true);
+    }
+    let mut options = LuaTable::default();
+    options.insert_table("globals", globals);
+    options.insert_bool("filesystem", self.filesystem);
+    options
+  }
+  /// Whether a module may be loaded by that name: without filesystem access, only paths that stay
+  /// inside the search directories.
+  pub fn allows_module(&self, name: &str) -> bool {
+    self.filesystem || Path::new(name).components().all(|it| matches!(it, Component::Normal(_)))
+  }
+}
+
+#[cfg(test)]
+#[test]
+fn test_sandbox() {
+  let sandbox = Sandbox::default();
+  assert!(sandbox.allows_module("Module:links/data"));
+  assert!(sandbox.allows_module("ustring/ustring"));
+  assert!(!sandbox.allows_module("../../etc/passwd"));
+  assert!(!sandbox.allows_module("/etc/passwd"));
+  assert!(!sandbox.allows_module("./links/data"));
+  assert!(Sandbox { filesystem: true, ..Sandbox::default() }.allows_module("/etc/passwd"));
+
+  let globals = sandbox.options().get_table("globals").unwrap();
+  assert!(globals.get_bool("mw").is_some());
+  assert!(globals.get_bool("loadstring").is_none());
+  assert!(globals.get_bool("io").is_none());
+  let options = Sandbox { loadstring: true, ..Sandbox::default() }.options();
+  assert!(options.get_table("globals").unwrap().get_bool("loadstring").is_some());
+}
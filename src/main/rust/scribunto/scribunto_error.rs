@@ -0,0 +1,87 @@
+#[allow(unused)]
+use crate::scribunto as m;
+use m::{php_error::PhpError, AnyLua, LuaTable, LuaTypeError};
+
+use std::fmt::{Debug, Display};
+
+/// Everything a `LuaInstance` or `Telua` call may fail with. `Lua` is a bug in the module being run,
+/// the rest mean the bridge itself is broken or overloaded.
+#[derive(thiserror::Error, Debug)]
+pub enum ScribuntoError {
+  #[error("transport: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("protocol violation: {0}")]
+  Protocol(String),
+  #[error("lua error: {0}")]
+  Lua(LuaFailed),
+  #[error("no such function: {0}")]
+  NoSuchFunction(String),
+  #[error("module not found: {0}")]
+  ModuleNotFound(String),
+  #[error("{0} limit exceeded")]
+  LimitExceeded(Limit),
+  #[error("unexpected value: {0}")]
+  Conversion(#[from] LuaTypeError),
+  #[error("sandbox violation: {0}")]
+  Sandbox(String),
+  #[error("interpreter killed, there is no builder to spawn another")]
+  Killed,
+}
+impl ScribuntoError {
+  pub fn protocol<T: Display>(message: T) -> Self {
+    Self::Protocol(message.to_string())
+  }
+}
+impl<I: Debug> From<nom::Err<PhpError<I>>> for ScribuntoError {
+  fn from(src: nom::Err<PhpError<I>>) -> Self {
+    match src {
+      nom::Err::Incomplete(needed) => Self::protocol(format!("incomplete message: {:?}", needed)),
+      nom::Err::Error(e) | nom::Err::Failure(e) => Self::protocol(e),
+    }
+  }
+}
+impl From<hex::FromHexError> for ScribuntoError {
+  fn from(src: hex::FromHexError) -> Self {
+    Self::protocol(format!("bad header: {}", src))
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+  WallTime,
+  CpuTime,
+  Memory,
+}
+impl Display for Limit {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      Self::WallTime => "wall-clock time",
+      Self::CpuTime => "cpu time",
+      Self::Memory => "memory",
+    })
+  }
+}
+
+/// Error raised inside Lua, with the structured backtrace attached by `MWServer:attachTrace`.
+#[derive(Debug)]
+pub struct LuaFailed {
+  pub value: AnyLua,
+  pub trace: LuaTable,
+}
+impl Display for LuaFailed {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match &self.value {
+      AnyLua::String(value) => f.write_str(&value.to_string_lossy())?,
+      value => write!(f, "{}", value)?,
+    }
+    for (_, frame) in self.trace.iter() {
+      if let AnyLua::Table(frame) = frame {
+        let text = |name: &str| frame.get_string(name).map(|it| it.to_string_lossy().into_owned());
+        let line = frame.get_integer("currentline").map(i64::from).unwrap_or_default();
+        let name = text("name").unwrap_or_else(|| "?".to_owned());
+        write!(f, "\n  {}:{}: in {}", text("short_src").unwrap_or_default(), line, name)?;
+      }
+    }
+    Ok(())
+  }
+}
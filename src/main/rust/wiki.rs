@@ -1,215 +1,262 @@
-use crate::{remote, scribunto::*};
+use crate::{lua_table, remote, scribunto::*};
+use ast::{Node, Template};
+use catalogue::{Catalogue, Edition};
 use jnino::JFace;
 use language::Language;
-use std::{
-  collections::{HashMap, HashSet},
-  rc::Rc,
-};
-use text::Text;
-use word_section::WordSection;
+pub use pool::TeluaPool;
+pub use proto::Proto;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+pub use tags::TagHandlers;
+pub use testcases::TestReport;
+use transclusion::View;
+use word_section::{Outline, WordSection};
 
+pub mod ast;
+mod catalogue;
 mod language;
+mod pool;
+mod proto;
 mod section;
 pub(crate) mod substr;
-mod text;
+mod tags;
+mod testcases;
+mod transclusion;
 mod wiki_error;
 mod word_section;
 
-#[derive(serde::Deserialize)]
-pub enum TypeId {
-  Bool,
-  Float,
-  Integer,
-  String,
-}
-#[derive(Default, serde::Deserialize)]
-pub struct Proto(HashMap<String, TypeId>);
-
-enum AnyParse<'a> {
-  Language(Language<(), WordSection<()>>),
-  Section(WordSection<()>),
-  Content(&'a str),
-}
-impl<'a> AnyParse<'a> {
-  fn parse(input: &'a str) -> Self {
-    if let Ok(r) = Language::parse(input).map(|v| v.1).map(Self::Language) {
-      r
-    } else if let Ok(r) = WordSection::parse(input).map(|v| v.1).map(Self::Section) {
-      r
-    } else {
-      Self::Content(input)
+fn parse_page(
+  page: &str,
+  edition: &Edition,
+  language: &str,
+  tags: &TagHandlers,
+  templates: &Path,
+  subwords: &mut HashSet<String>,
+  pool: &TeluaPool,
+) -> TeluaResult<Option<Vec<Outline<String>>>> {
+  let mut nodes = ast::parse(&transclusion::include(page, View::Direct)).into_iter();
+  let lang = nodes.by_ref().find_map(|node| {
+    match node {
+      Node::Heading(heading) => Language::parse(&edition.line(&heading)).ok().map(|it| it.1),
+      _ => None,
+    }
+    .filter(|it| it.name == language)
+  });
+  let mut lang = match lang {
+    Some(lang) => lang.convert(|_| unreachable!()),
+    None => return Ok(None),
+  };
+  for node in nodes {
+    match node {
+      Node::Heading(heading) if edition.level(&heading) <= 2 => break,
+      Node::Heading(heading) => match WordSection::parse(&edition.line(&heading), edition) {
+        Ok((_, section)) => lang.sections.push(section.convert(|_| unreachable!())),
+        Err(_) => lang.section().children.push(Node::Heading(heading)),
+      },
+      node => lang.section().children.push(node),
     }
   }
+  // every list item is an entry of its own, `»` marking how deep it is
+  let lang = lang.fold_convert(|mut acc: Vec<Vec<Node>>, node: Node| -> Vec<Vec<Node>> {
+    fn items(acc: &mut Vec<Vec<Node>>, list: ast::List, depth: usize) {
+      for item in list.items {
+        let mut entry = vec![Node::Text("»".repeat(depth) + " ")];
+        entry.extend(item.content);
+        acc.push(entry);
+        item.nested.into_iter().for_each(|it| items(acc, it, depth + 1));
+      }
+    }
+    match node {
+      Node::List(list) => items(&mut acc, list, 1),
+      Node::Paragraph(children) => acc.push(children),
+      Node::HorizontalRule | Node::Comment(_) => {}
+      node => acc.push(vec![node]),
+    }
+    acc
+  });
+  // one instance for the whole page, it is reset once it goes back to the pool
+  let mut telua = pool.get()?;
+  let frame = telua.new_frame(Default::default(), None)?;
+  let lang =
+    lang.convert(|nodes| convert_nodes(&nodes, tags, templates, subwords, &mut telua, &frame).trim().to_owned());
+  Ok(Some(lang.outline().entries(&|it| it.splits())))
 }
 
-fn parse_page(
-  page: &str,
-  language: &str,
+fn convert_nodes(
+  nodes: &[Node],
+  tags: &TagHandlers,
+  templates: &Path,
   subwords: &mut HashSet<String>,
-) -> Result<Vec<Language<String, Rc<WordSection<String>>>>, ()> {
-  let mut iter = page.lines();
-  let mut lang = {
-    let iter = &mut iter;
-    (move || {
-      for line in iter {
-        match Language::parse(line) {
-          Ok(lang) if lang.1.name == language => return Ok(lang.1),
-          Ok(_) => {}
-          Err(nom::Err::Error(e)) if !e.filtered() => return Err(()),
-          Err(_) => {}
+  telua: &mut Telua,
+  frame: &Frame,
+) -> String {
+  let mut out = String::new();
+  for node in nodes {
+    match node {
+      Node::Text(text) => out += text,
+      Node::Paragraph(children) | Node::Bold(children) | Node::Italic(children) => {
+        out += &convert_nodes(children, tags, templates, subwords, telua, frame)
+      }
+      Node::Heading(heading) => out += &convert_nodes(&heading.title, tags, templates, subwords, telua, frame),
+      Node::Link(link) => {
+        subwords.insert(link.target.clone());
+        match &link.label {
+          Some(label) => out += &convert_nodes(label, tags, templates, subwords, telua, frame),
+          None => out += &link.target,
         }
       }
-      Err(())
-    })()?
-  }
-  .convert(|_| unreachable!());
-  for line in iter {
-    let any = AnyParse::parse(line);
-    match any {
-      AnyParse::Language(_) => break,
-      AnyParse::Section(section) => {
-        lang.sections.push(section.convert(|_| unreachable!()));
+      Node::ExternalLink(link) => match &link.label {
+        Some(label) => out += &convert_nodes(label, tags, templates, subwords, telua, frame),
+        None => out += &link.url,
+      },
+      Node::Template(template) => out += &convert_template(template, tags, templates, subwords, telua, frame),
+      Node::Parameter(parameter) => {
+        // names are trimmed, values and defaults are not: the caller trimmed the named ones already
+        let name = convert_nodes(&parameter.name, tags, templates, subwords, telua, frame).trim().to_owned();
+        match (frame.arg(&name), &parameter.default) {
+          (Some(value), _) => out += &value,
+          (None, Some(default)) => out += &convert_nodes(default, tags, templates, subwords, telua, frame),
+          (None, None) => out += &format!("{{{{{{{}}}}}}}", name),
+        }
       }
-      AnyParse::Content(content) => {
-        lang.section().children.push(content.trim());
+      Node::Tag(tag) => {
+        out += &tags.render(tag, &mut |nodes| convert_nodes(nodes, tags, templates, subwords, telua, frame))
       }
-    }
-  }
-  let lang = lang.fold_convert(|mut acc: Vec<String>, line: &str| -> Vec<String> {
-    if line.is_empty() {
-      acc.push(String::new());
-    } else {
-      if line.chars().next().map(|prefix| matches!(prefix, '#' | '*')).unwrap_or_default() {
-        acc.push(line.to_owned());
-      } else {
-        if let Some(last) = acc.last_mut() {
-          *last += " ";
-          *last += line;
-        } else {
-          acc.push(line.to_owned());
+      Node::List(list) => {
+        for item in &list.items {
+          out += &convert_nodes(&item.content, tags, templates, subwords, telua, frame);
         }
       }
-    }
-    acc
-  });
-  let lang = lang.try_convert(|src: String| -> Result<Vec<Text>, ()> {
-    let mut src = src.as_str();
-    let mut out = Vec::new();
-    while !src.is_empty() {
-      let (s, text) = Text::parse(&src, subwords).map_err(|e| {
-        eprintln!("\x1b[31mError\x1b[0m: {:?} during parsing «{}»", e, src);
-      })?;
-      src = s;
-      out.push(text);
-    }
-    Ok(out)
-  })?;
-  let converter = |text: Vec<Text>| -> String {
-    let mut telua = Telua::new().unwrap();
-    fn convert_text(text: Vec<Text>, subwords: &mut HashSet<String>, telua: &mut Telua, frame: &Frame) -> String {
-      let mut out = String::new();
-      for text in text {
-        if !out.is_empty() {
-          out += " ";
+      Node::Table(table) => {
+        if let Some(caption) = &table.caption {
+          out += convert_nodes(&caption.content, tags, templates, subwords, telua, frame).trim();
         }
-        match text {
-          Text::Raw(raw) => out += raw.as_str(),
-          Text::Tab(tab) => {
-            for _ in (0..).take(tab as usize) {
-              out += "»";
-            }
-          }
-          Text::Template(template) => {
-            let args: HashMap<String, String> =
-              template.args.into_iter().map(|it| (it.0, convert_text(it.1 .1, subwords, telua, frame))).collect(); // ??? TODO
-            let mut com = template.com;
-            if matches!(com[0], Text::Tab(1)) {
-              com[0] = Text::Raw("#".to_string());
-            }
-            let com = convert_text(com, subwords, telua, frame);
-            if com.starts_with("#") {
-              if let Some(module) = com.strip_prefix("# invoke:") {
-                let mut i = module.splitn(2, ":");
-                let module = i.next().unwrap();
-                let function = i.next().unwrap();
-                println!("\x1b[32mM:{}\x1b[0m", module);
-                let proto: Proto = serde_json::from_reader(
-                  std::fs::File::open(format!("/tmp/widictor/modules/{}.proto", &module)).unwrap(),
-                )
-                .unwrap();
-                let frame = telua.new_frame(args, proto, Some(&frame)).unwrap();
-                let _module = telua.call(&module, &function, frame).unwrap();
-                //out += format!("{{MODULE: {:#?}}}", module).as_str();
-                //panic!("{}", out);
-              } else {
-                panic!("unknown: {}", com);
-              }
-              // #invoke:etymology/templates|inherited Module:…|function
-            } else {
-              match com.as_str() {
-                "PAGENAME" => out += "PAGENAME",
-                _ => {
-                  if template.is_defval {
-                    println!("\x1b[33mD: {} — {:?}", &com, &args);
-                  } else {
-                    println!("\x1b[32mT:{}\x1b[0m", com);
-                    let file = format!("/tmp/widictor/{}", com);
-                    let source = std::fs::read_to_string(file).unwrap();
-                    //let page = clean_raw(remote::get(&format!("Template:{}", com)).unwrap());
-                    let page = source;
-                    let page = page.lines().fold(Vec::new(), |mut acc, it| {
-                      let mut tail = it;
-                      while !tail.is_empty() {
-                        let (t, v) = Text::parse(tail, subwords).unwrap();
-                        tail = t;
-                        acc.push(v);
-                      }
-                      acc
-                    });
-                    out += &convert_text(page, subwords, telua, frame);
-                  }
-                }
-              }
-            }
-          }
+        // a line for each row of the grid, a cell that spans is read once and shown wherever it is
+        let mut cells: HashMap<*const ast::Cell, String> = HashMap::new();
+        for row in table.grid() {
+          let row: Vec<String> = row
+            .into_iter()
+            .map(|cell| match cell {
+              Some(cell) => cells
+                .entry(cell)
+                .or_insert_with(|| {
+                  convert_nodes(&cell.content, tags, templates, subwords, telua, frame).trim().to_owned()
+                })
+                .clone(),
+              None => String::new(),
+            })
+            .collect();
+          out += "\n";
+          out += &row.join(" | ");
         }
       }
-      out
+      Node::HorizontalRule | Node::Comment(_) => {}
     }
-    let frame = telua.new_frame(Default::default(), Default::default(), None).unwrap();
-    convert_text(text, subwords, &mut telua, &frame)
-  };
+  }
+  out
+}
+
+fn convert_template(
+  template: &Template,
+  tags: &TagHandlers,
+  templates: &Path,
+  subwords: &mut HashSet<String>,
+  telua: &mut Telua,
+  frame: &Frame,
+) -> String {
+  let name = convert_nodes(&template.name, tags, templates, subwords, telua, frame).trim().to_owned();
+  // MediaWiki trims named arguments only
+  let mut positional = Vec::new();
+  let mut named = Vec::new();
+  for arg in &template.args {
+    let value = convert_nodes(&arg.value, tags, templates, subwords, telua, frame);
+    match &arg.name {
+      Some(name) => named.push((name.clone(), value.trim().to_owned())),
+      None => positional.push(value),
+    }
+  }
+  if let Some(module) = name.strip_prefix("#invoke:") {
+    let module = module.trim();
+    // the function is the first argument, the module sees the rest
+    if positional.is_empty() {
+      eprintln!("\x1b[31mError\x1b[0m: no function in #invoke:{}", module);
+      return String::new();
+    }
+    let function = positional.remove(0).trim().to_owned();
+    let mut args: HashMap<String, String> =
+      positional.into_iter().enumerate().map(|(id, it)| ((id + 1).to_string(), it)).chain(named).collect();
+    println!("\x1b[32mM:{}\x1b[0m", module);
+    let proto = Proto::load(crate::dirs::modules().join(format!("{}.proto", &module)));
+    if let Err(e) = proto.and_then(|proto| proto.map_or(Ok(()), |it| it.apply(&mut args))) {
+      eprintln!("\x1b[31mError\x1b[0m: {} in #invoke:{}|{}", e, module, function);
+      return String::new();
+    }
+    match telua.new_frame(args, Some(frame)).and_then(|frame| telua.call(module, &function, frame)) {
+      Ok(out) => out,
+      Err(e) => {
+        eprintln!("\x1b[31mError\x1b[0m: {} in #invoke:{}|{}", e, module, function);
+        String::new()
+      }
+    }
+  } else if name.starts_with('#') {
+    log::warn!("unknown parser function: {}", name);
+    String::new()
+  } else if name == "PAGENAME" {
+    "PAGENAME".to_owned()
+  } else {
+    println!("\x1b[32mT:{}\x1b[0m", name);
+    // a template that can't be read shows as the link to it, the way MediaWiki shows a missing one
+    let missing = |e: &dyn std::fmt::Display| {
+      eprintln!("\x1b[31mError\x1b[0m: {} in {{{{{}}}}}", e, name);
+      format!("[[Template:{}]]", name)
+    };
+    let source = match std::fs::read_to_string(templates.join(&name)) {
+      Ok(source) => source,
+      Err(e) => return missing(&e),
+    };
+    // the template sees its own arguments, numbered as they come
+    let args = positional.into_iter().enumerate().map(|(id, it)| ((id + 1).to_string(), it)).chain(named).collect();
+    convert_nodes(
+      &ast::parse_fragment(&transclusion::include(&source, View::Transcluded)),
+      tags,
+      templates,
+      subwords,
+      telua,
+      &frame.nested(args),
+    )
+  }
+}
 
-  let lang = lang.convert(converter);
-  Ok(lang.subdivide())
+// where `loadPackage` finds a package: `Module:` pages are in the modules directory under their title
+fn package_file(name: &str) -> String {
+  match name.strip_prefix("Module:") {
+    Some(title) => format!("{}.lua", title),
+    None if name == "ustring" => "ustring/ustring.lua".to_owned(),
+    None => format!("{}.lua", name),
+  }
 }
 
 #[derive(JFace)]
 pub struct Telua {
-  pub machine: LuaInstance<std::process::ChildStdout, std::process::ChildStdin>,
-  libs: HashMap<&'static str, LuaTable<LuaString>>,
+  pub machine: Box<dyn LuaEngine>,
+  libs: HashMap<&'static str, LuaTable>,
+  // how to get a new interpreter when the current one is killed, and what it is recorded to
+  builder: Option<LuaBuilder>,
+  recorder: Option<Recorder>,
+  sandbox: Sandbox,
 }
-type TeluaError = Box<dyn std::error::Error>;
-type TeluaResult<T> = Result<T, TeluaError>;
-type ApiMap = HashMap<
-  &'static str,
-  Box<
-    dyn Fn(
-      &mut LuaInstance<std::process::ChildStdout, std::process::ChildStdin>,
-      LuaTable<LuaInteger>,
-    ) -> LuaTable<LuaInteger>,
-  >,
->;
+type TeluaResult<T> = ScribuntoResult<T>;
+type ApiMap = HashMap<&'static str, LuaCallback>;
 impl Telua {
   pub fn empty() -> TeluaResult<Self> {
-    let machine = LuaInstance::new(
-      "pkg/mw_main.lua",
-      "pkg",
-      0,
-      4,
-      vec!["pkg".to_owned(), "pkg/ustring".to_owned(), "/tmp/widictor/modules".to_owned()],
-    )?;
-    Ok(Self { machine, libs: Default::default() })
+    let builder = LuaInstance::builder();
+    let mut telua = Self::weld(Box::new(builder.spawn()?));
+    telua.builder = Some(builder);
+    Ok(telua)
+  }
+  /// Wraps an engine without running any of the `mw` setup.
+  pub fn weld(machine: Box<dyn LuaEngine>) -> Self {
+    Self { machine, libs: Default::default(), builder: None, recorder: None, sandbox: Default::default() }
   }
   fn mw_init(&mut self) -> TeluaResult<()> {
     self.machine.call_file("mwInit_lua", "mwInit.lua")?;
@@ -235,55 +282,43 @@ impl Telua {
   }
   fn mw_interface_2(&mut self) -> TeluaResult<()> {
     let mut api = ApiMap::new();
+    let sandbox = self.sandbox.clone();
     api.insert(
       "loadPackage",
-      Box::new(|instance, args| {
-        let file_id = args.get_string(1).unwrap().as_raw().to_owned();
-        let file = if let Some(file_id) = file_id.strip_prefix("Module:") {
-          format!("{}.lua", file_id)
-        } else {
-          let file_id = match file_id.as_str() {
-            "ustring" => "ustring/ustring",
-            u => u,
-          };
-          format!("{}.lua", file_id)
-        };
-        let chunk = instance.load_file(&file_id, &file).unwrap();
-        let mut out = LuaTable::default();
-        out.insert_chunk(1, chunk);
-        out
+      Box::new(move |instance, args| {
+        let file_id: String = args.get_as(1)?;
+        if !sandbox.allows_module(&file_id) {
+          log::warn!("sandbox: refused to load package {}", file_id);
+          return Ok(LuaTable::default());
+        }
+        match instance.load_file(&file_id, &package_file(&file_id)) {
+          Ok(chunk) => Ok(lua_table![chunk]),
+          Err(ScribuntoError::ModuleNotFound(_)) => Ok(lua_table! {}),
+          Err(e) => Err(e),
+        }
       }),
     );
+    let sandbox = self.sandbox.clone();
     api.insert(
       "loadPHPLibrary",
-      Box::new(|instance, args| {
-        let file_id = args.get_string(1).unwrap().as_raw().to_owned();
-        let file = if let Some(id) = file_id.strip_prefix("Module:") { id.to_owned() } else { file_id };
-        let api = instance.call_file(&file, &format!("{}.lua", &file)).unwrap();
-        if let Some(mut api) = api.get_string_table(1) {
-          let mut old_api = Default::default();
-          std::mem::swap(&mut api.value, &mut old_api);
-          api.value = old_api
-            .into_iter()
-            .map(|(name, val)| {
-              if let Some(val) = val.as_string_table() {
-                if let Some(id) = val.object.as_ref() {
-                  if id == "Scribunto_LuaStandaloneInterpreterFunction" {
-                    let f = val.get_integer("id").unwrap();
-                    return (name, Box::new(f.to_chunk().into()));
-                  }
-                }
-              }
-              (name, val)
-            })
-            .collect();
-
-          let mut wrap = LuaTable::default();
-          wrap.insert_string_table(1, api);
-          wrap
-        } else {
-          api
+      Box::new(move |instance, args| {
+        let file_id: String = args.get_as(1)?;
+        if !sandbox.allows_module(&file_id) {
+          log::warn!("sandbox: refused to load library {}", file_id);
+          return Ok(LuaTable::default());
+        }
+        // modules are packages, they must run in the environment of whoever requires them
+        if file_id.starts_with("Module:") {
+          return Ok(LuaTable::default());
         }
+        instance.call_file(&file_id, &format!("{}.lua", &file_id))
+      }),
+    );
+    api.insert(
+      "dataVersion",
+      Box::new(|instance, args| {
+        let file_id: String = args.get_as(1)?;
+        Ok(lua_table![instance.fingerprint(&file_id, &package_file(&file_id))])
       }),
     );
     api.insert("frameExists", Box::new(|_, _| todo!()));
@@ -328,14 +363,7 @@ impl Telua {
   }
   fn mw_interface_6(&mut self) -> TeluaResult<()> {
     let mut api = ApiMap::new();
-    api.insert(
-      "getContLangCode",
-      Box::new(|_, _| {
-        let mut ret = LuaTable::default();
-        ret.insert_string(1, "la");
-        ret
-      }),
-    );
+    api.insert("getContLangCode", Box::new(|_, _| Ok(lua_table!["la"])));
     api.insert("isSupportedLanguage", Box::new(|_, _| todo!()));
     api.insert("isKnownLanguageTag", Box::new(|_, _| todo!()));
     api.insert("isValidCode", Box::new(|_, _| todo!()));
@@ -400,11 +428,24 @@ impl Telua {
     api.insert("hashValue", Box::new(|_, _| todo!()));
     self.register_library("mw_interface", 11, api)
   }
-  fn setup_interface<F: Fn(&mut LuaTable<LuaString>)>(&mut self, name: &'static str, arg_gen: F) -> TeluaResult<()> {
-    let lib = self.machine.call_file(name, &format!("{}.lua", name))?.get_string_table(1).unwrap();
-    let setup = lib.get_function("setupInterface").ok_or_else(|| format!("setupInterface not found for {}", name))?;
+  fn lib_function(&self, lib: &str, name: &str) -> TeluaResult<LuaChunk> {
+    self
+      .libs
+      .get(lib)
+      .and_then(|it| it.get_function(name))
+      .ok_or_else(|| ScribuntoError::NoSuchFunction(format!("{}.{}", lib, name)))
+  }
+  fn setup_interface<F: Fn(&mut LuaTable)>(&mut self, name: &'static str, arg_gen: F) -> TeluaResult<()> {
+    let lib = self
+      .machine
+      .call_file(name, &format!("{}.lua", name))?
+      .get_table(1)
+      .ok_or_else(|| ScribuntoError::protocol(format!("{} returned no table", name)))?;
+    let setup = lib
+      .get_function("setupInterface")
+      .ok_or_else(|| ScribuntoError::NoSuchFunction(format!("{}.setupInterface", name)))?;
     let mut args = LuaTable::default();
-    args.insert_string_table(1, {
+    args.insert_table(1, {
       let mut args = LuaTable::default();
       arg_gen(&mut args);
       args
@@ -413,14 +454,51 @@ impl Telua {
     self.libs.insert(name, lib);
     Ok(())
   }
-  pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-    let mut machine = Self::empty()?;
+  pub fn new() -> TeluaResult<Self> {
+    Self::build(LuaInstance::builder())
+  }
+  /// Starts an interpreter the way `builder` says and brings up the `mw` environment on it.
+  pub fn build(builder: LuaBuilder) -> TeluaResult<Self> {
+    Self::build_sandboxed(builder, Sandbox::default())
+  }
+  /// Same as `build`, with modules held to `sandbox` instead of the default policy.
+  pub fn build_sandboxed(builder: LuaBuilder, sandbox: Sandbox) -> TeluaResult<Self> {
+    Self::spawn(builder, sandbox, None)
+  }
+  /// Same as `new`, with the whole session written down to `path`.
+  pub fn recording<P: AsRef<std::path::Path>>(path: P) -> TeluaResult<Self> {
+    Self::spawn(LuaInstance::builder(), Sandbox::default(), Some(Recorder::create(path)?))
+  }
+  // the recorder goes on before the setup, so a replay goes through it as well
+  fn spawn(builder: LuaBuilder, sandbox: Sandbox, recorder: Option<Recorder>) -> TeluaResult<Self> {
+    let mut machine = builder.spawn()?;
+    if let Some(recorder) = recorder.clone() {
+      machine.record(recorder);
+    }
+    let mut telua = Self::with_sandbox(Box::new(machine), sandbox)?;
+    telua.builder = Some(builder);
+    telua.recorder = recorder;
+    Ok(telua)
+  }
+  /// Plays a session written by `recording` back, no interpreter is needed.
+  pub fn replay<P: AsRef<std::path::Path>>(path: P) -> TeluaResult<Self> {
+    Self::with_engine(Box::new(LuaInstance::replay(path)?))
+  }
+  /// Brings up the `mw` environment on the given engine.
+  pub fn with_engine(engine: Box<dyn LuaEngine>) -> TeluaResult<Self> {
+    Self::with_sandbox(engine, Sandbox::default())
+  }
+  /// Brings up the `mw` environment on the given engine and locks it down to `sandbox`.
+  pub fn with_sandbox(engine: Box<dyn LuaEngine>, sandbox: Sandbox) -> TeluaResult<Self> {
+    let mut machine = Self::weld(engine);
+    let env_funcs = sandbox.env_funcs;
+    machine.sandbox = sandbox;
     machine.mw_interface_1()?;
     machine.mw_init()?;
     machine.setup_interface("mw.frame", |_| {})?;
     machine.mw_interface_2()?;
     machine.setup_interface("mw", |it| {
-      it.insert_bool("allowEnvFuncs", false);
+      it.insert_bool("allowEnvFuncs", env_funcs);
     })?;
     machine.package()?;
     machine.mw_interface_3()?;
@@ -434,7 +512,7 @@ impl Telua {
       ]
       .iter()
       .for_each(|f| it.insert_string(f.0, f.1));
-      it.insert_integer_table("namespaces", LuaTable::default());
+      it.insert_table("namespaces", LuaTable::default());
       let stats =
         [("pages", 1), ("articles", 0), ("files", 0), ("edits", 0), ("users", 1), ("activeUsers", 1), ("admins", 1)]
           .iter()
@@ -442,7 +520,7 @@ impl Telua {
             acc.insert_integer(it.0, it.1);
             acc
           });
-      it.insert_string_table("stats", stats);
+      it.insert_table("stats", stats);
     })?;
     machine.mw_interface_4()?;
     machine.setup_interface("mw.uri", |_| {})?;
@@ -454,11 +532,9 @@ impl Telua {
     machine.mw_interface_6()?;
     machine.setup_interface("mw.language", |_| {})?;
     machine.mw_interface_7()?;
-    machine
-      .setup_interface("mw.message", |it| {
-        it.insert_string("lang", "la");
-      })
-      .unwrap();
+    machine.setup_interface("mw.message", |it| {
+      it.insert_string("lang", "la");
+    })?;
     machine.mw_interface_8()?;
     machine.setup_interface("mw.title", |it| {
       let mut this_title = LuaTable::default();
@@ -471,12 +547,12 @@ impl Telua {
       this_title.insert_string("fragment", "");
       this_title.insert_string("thePartialUrl", "Sample");
       this_title.insert_bool("file", false);
-      it.insert_string_table("thisTitle", this_title);
+      it.insert_table("thisTitle", this_title);
       it.insert_integer("NS_MEDIA", -2);
     })?;
     machine.mw_interface_9()?;
     machine.setup_interface("mw.text", |it| {
-      it.insert_string_table("nowiki_protocols", LuaTable::default());
+      it.insert_table("nowiki_protocols", LuaTable::default());
       it.insert_string("comma", ",");
       it.insert_string("and", " et ");
       it.insert_string("ellipsis", "...");
@@ -490,128 +566,237 @@ impl Telua {
     })?;
     machine.mw_interface_11()?;
     machine.setup_interface("mw.hash", |_| {})?;
+    machine.apply_sandbox()?;
 
     Ok(machine)
   }
+  // runs last, the libraries above may still need what it takes away
+  fn apply_sandbox(&mut self) -> TeluaResult<()> {
+    let apply = self
+      .machine
+      .call_file("sandbox", "sandbox.lua")?
+      .get_function(1)
+      .ok_or_else(|| ScribuntoError::protocol("sandbox.lua returned no function"))?;
+    let mut args = LuaTable::default();
+    args.insert_table(1, self.sandbox.options());
+    let out = self.machine.call(apply, args)?;
+    let names = |id: i64| -> TeluaResult<Vec<String>> {
+      let mut names = out.get_as::<_, Option<Vec<String>>>(id)?.unwrap_or_default();
+      names.sort();
+      Ok(names)
+    };
+    let removed = names(1)?;
+    if !removed.is_empty() {
+      log::info!("sandbox: removed {}", removed.join(", "));
+    }
+    let exposed = names(2)?;
+    if exposed.is_empty() {
+      Ok(())
+    } else {
+      Err(ScribuntoError::Sandbox(format!("still reachable: {}", exposed.join(", "))))
+    }
+  }
+  /// Forgets what the last page left behind: the log buffer and every chunk but the libraries' own,
+  /// loaded modules included. Frames are passed with each call, so there is nothing to drop for them.
+  pub fn reset(&mut self) -> TeluaResult<()> {
+    let clear = self.lib_function("mw", "clearLogBuffer")?;
+    self.machine.call(clear, LuaTable::default())?;
+    let owned = self.libs.values().flat_map(LuaTable::chunks).collect();
+    self.machine.cleanup_chunks(owned)?;
+    Ok(())
+  }
+  pub fn limits(&self) -> Limits {
+    self.machine.limits()
+  }
+  pub fn set_limits(&mut self, limits: Limits) {
+    self.machine.set_limits(limits);
+  }
+  // a killed interpreter is replaced by a new one set up the same way, so the next call starts from
+  // scratch; a welded one has nothing to be replaced with
+  fn respawn(&mut self) -> TeluaResult<()> {
+    let builder = self.builder.clone().ok_or(ScribuntoError::Killed)?;
+    let limits = self.limits();
+    *self = Self::spawn(builder, self.sandbox.clone(), self.recorder.clone())?;
+    self.set_limits(limits);
+    Ok(())
+  }
   pub fn call(&mut self, file: &str, function: &str, frame: Frame) -> TeluaResult<String> {
+    self.session(&format!("{}:{}", file, function), |telua| telua.invoke(file, function, frame))
+  }
+  // the module and whatever it returns are dropped once `run` is over
+  fn session<T, F: FnOnce(&mut Self) -> TeluaResult<T>>(&mut self, what: &str, run: F) -> TeluaResult<T> {
+    self.machine.begin_session();
+    match run(self) {
+      Err(ScribuntoError::LimitExceeded(limit)) => {
+        log::warn!("{} exceeded {} limit, respawning the interpreter", what, limit);
+        self.respawn()?;
+        Err(ScribuntoError::LimitExceeded(limit))
+      }
+      Ok(out) => {
+        self.machine.end_session()?;
+        Ok(out)
+      }
+      // what went wrong in `run` is what the caller needs to know, a broken pipe fails the cleanup too
+      Err(e) => {
+        if let Err(cleanup) = self.machine.end_session() {
+          log::warn!("can't release the chunks of {}: {}", what, cleanup);
+        }
+        Err(e)
+      }
+    }
+  }
+  fn invoke(&mut self, file: &str, function: &str, frame: Frame) -> TeluaResult<String> {
     let chunk = self.machine.load_file(file, &format!("{}.lua", file))?;
-    let mw = self.libs.get("mw").unwrap();
-    let execute_module = mw.get_function("executeModule").unwrap();
+    let execute_module = self.lib_function("mw", "executeModule")?;
     let mut args = LuaTable::default();
     args.insert_chunk(1, chunk);
     args.insert_string(2, function);
-    args.insert_string_table(3, frame.clone().into_raw());
+    args.insert_table(3, frame.clone().into_raw());
     let out = self.machine.call(execute_module, args)?;
-    let out = out.get_function(2).unwrap();
+    let out = out.get_function(2).ok_or_else(|| ScribuntoError::NoSuchFunction(format!("{}.{}", file, function)))?;
     let mut args = LuaTable::default();
-    args.insert_string_table(1, frame.into_raw());
+    args.insert_table(1, frame.into_raw());
     let out = self.machine.call(out, args)?;
-    panic!("{:#?}", out);
-
-    // let table = self.machine.call_file(file, &format!("{}.lua", file))?;
-    // let table = table.get_string_table(1).unwrap();
-    // let function = table.get_function(function).unwrap();
-    // let mut table = LuaTable::default();
-    // table.insert_string_table(1, frame);
-    // let out = self.machine.call(function, table)?;
-    // panic!("{:#?}", out);
-  }
-  pub fn new_frame(
-    &mut self,
-    args: HashMap<String, String>,
-    proto: Proto,
-    parent: Option<&Frame>,
-  ) -> TeluaResult<Frame> {
-    let mut table = LuaTable::<LuaString>::default();
-    for arg in args {
-      if let Some(tid) = proto.0.get(&arg.0) {
-        match tid {
-          TypeId::Bool => table.insert_bool(arg.0, arg.1 == "true"),
-          TypeId::Float => table.insert_float(arg.0, arg.1.parse::<f32>().unwrap()),
-          TypeId::Integer => table.insert_integer(arg.0, arg.1.parse::<i32>().unwrap()),
-          TypeId::String => table.insert_string(arg.0, &arg.1),
-        }
-      } else {
-        table.insert_string(arg.0, &arg.1)
-      }
-    }
+    // the same as `mw.executeFunction` does with the results
+    Ok(
+      out
+        .into_iter()
+        .map(|(_, it)| match it {
+          AnyLua::String(it) => it.to_string_lossy().into_owned(),
+          it => it.to_string(),
+        })
+        .collect(),
+    )
+  }
+  /// Arguments are strings, as MediaWiki passes them; positional ones are numbered, `frame.args[1]`.
+  /// Checking them against a `Proto` is up to the caller.
+  pub fn new_frame(&mut self, args: HashMap<String, String>, parent: Option<&Frame>) -> TeluaResult<Frame> {
     if let Some(frame) = parent {
-      let lambda = self.libs.get("mw.frame").unwrap().get_function("getParentFrame").unwrap();
-      Ok(frame.child(table, lambda))
+      let lambda = self.lib_function("mw.frame", "getParentFrame")?;
+      Ok(frame.child(Frame::args(args), lambda))
     } else {
-      Ok(Frame::new(table))
+      Ok(Frame::new(Frame::args(args)))
     }
   }
 }
 
 #[derive(Clone)]
-pub struct Frame(LuaTable<LuaString>);
+pub struct Frame(LuaTable);
 impl Frame {
-  pub fn new(args: LuaTable<LuaString>) -> Self {
+  pub fn new(args: LuaTable) -> Self {
     let mut table = LuaTable::default();
-    table.insert_string_table("args", args);
+    table.insert_table("args", args);
     Self(table)
   }
-  pub fn child(&self, args: LuaTable<LuaString>, lambda: LuaChunk) -> Self {
-    let mut child = Self::new(args);
-    child.0.insert_string_table("parent", self.0.clone());
+  pub fn child(&self, args: LuaTable, lambda: LuaChunk) -> Self {
+    let mut child = self.nested_table(args);
     child.0.insert_chunk("getParent", lambda);
     child
   }
-  pub fn into_raw(self) -> LuaTable<LuaString> {
+  /// The frame of a template called from this one. Only `{{{…}}}` reads it, so it is made without
+  /// the interpreter: a module the template invokes gets a frame of its own, with `getParent`.
+  pub fn nested(&self, args: HashMap<String, String>) -> Self {
+    self.nested_table(Self::args(args))
+  }
+  fn nested_table(&self, args: LuaTable) -> Self {
+    let mut child = Self::new(args);
+    child.0.insert_table("parent", self.0.clone());
+    child
+  }
+  // positional arguments go under integer keys, the way `frame.args[1]` reads them
+  fn args(args: HashMap<String, String>) -> LuaTable {
+    let mut table = LuaTable::default();
+    for (name, value) in args {
+      match name.parse::<i64>() {
+        Ok(id) if id > 0 && id.to_string() == name => table.insert_string(id, value),
+        _ => table.insert_string(name, value),
+      }
+    }
+    table
+  }
+  /// The argument `name` the frame was given, `1` being the first positional one.
+  pub fn arg(&self, name: &str) -> Option<String> {
+    let args = self.0.get_table("args")?;
+    let value = match name.parse::<i64>() {
+      Ok(id) if id > 0 && id.to_string() == name => args.get_string(id),
+      _ => args.get_string(name),
+    }?;
+    Some(String::from_utf8_lossy(value.as_ref()).into_owned())
+  }
+  pub fn into_raw(self) -> LuaTable {
     self.into()
   }
 }
-impl From<Frame> for LuaTable<LuaString> {
+impl From<Frame> for LuaTable {
   fn from(src: Frame) -> Self {
     src.0
   }
 }
 
-fn clean_raw(src: String) -> String {
-  let mut opened = false;
-  let mut tag = String::new();
-  let mut noinclude = false;
-  let mut out = String::new();
-  for c in src.chars() {
-    match c {
-      '<' => opened = true,
-      '>' => {
-        match tag.as_str() {
-          "noinclude" => noinclude = true,
-          "/noinclude" => noinclude = false,
-          com if com.starts_with("!--") => {}
-          _ => log::warn!("unknown tag: {}", tag),
-        }
-        tag.clear();
-        opened = false
-      }
-      s if opened => tag.push(s),
-      _ if noinclude => {}
-      s => out.push(s),
-    }
-  }
-  if let Some(redirect) = out.strip_prefix("#REDIRECT ") {
+// the page a redirect leads to, tags and comments are left for the parser
+fn clean_raw(src: String, edition: &str) -> String {
+  if let Some(redirect) = src.strip_prefix("#REDIRECT ") {
     let link = redirect.trim().strip_prefix("[[").unwrap().strip_suffix("]]").unwrap();
-    clean_raw(remote::get(&link).unwrap())
+    clean_raw(remote::get(edition, link).unwrap(), edition)
   } else {
-    out
+    src
   }
 }
+thread_local! {
+  // instances live as long as the thread, so consecutive scans skip the interpreter setup
+  static POOL: TeluaPool = TeluaPool::new(2);
+}
+/// Prints the entries `language` has on the page `word`. `fr:chat` is the page on the French
+/// edition, where languages are named as that edition has them: `fr` there, `Deutsch` on `de:`.
 pub fn scan(word: &str, language: &str) {
-  let page = remote::get(word).map(clean_raw).unwrap();
+  scan_with(word, language, &TagHandlers::builtin())
+}
+/// Same as `scan`, with tags rendered by `tags`: the built-in ones and whatever extensions the wiki
+/// has, `TagHandlers::register` adds them.
+pub fn scan_with(word: &str, language: &str, tags: &TagHandlers) {
+  let (code, edition, word) = Catalogue::global().split(word);
+  let page = remote::get(code, word).map(|it| clean_raw(it, code)).unwrap();
   let mut subwords = HashSet::new();
-  let words = parse_page(&page, &language, &mut subwords).unwrap();
+  let templates = crate::dirs::templates();
+  let words = match POOL.with(|pool| parse_page(&page, edition, language, tags, &templates, &mut subwords, pool)) {
+    Ok(Some(words)) => words,
+    Ok(None) => {
+      eprintln!("\x1b[31mError\x1b[0m: no {} on {}", language, word);
+      return;
+    }
+    Err(e) => {
+      eprintln!("\x1b[31mError\x1b[0m: {} while reading {}", e, word);
+      return;
+    }
+  };
   for (id, page) in words.into_iter().enumerate() {
     println!("{} — {}:", word, id);
-    for section in page.sections.iter().rev() {
-      println!("  {:?}:", section.name);
+    for (depth, section) in page.sections() {
+      let indent = "  ".repeat(depth + 1);
+      match &section.name {
+        section::Section::Other(name) => println!("{}{}:", indent, name),
+        name => println!("{}{:?}:", indent, name),
+      }
       for child in &section.children {
-        println!("    {}", child);
+        println!("{}  {}", indent, child);
       }
     }
   }
 }
+/// Runs `Module:<module>/testcases` and prints the report, true when every test passed.
+pub fn run_tests(module: &str) -> bool {
+  let report: TeluaResult<TestReport> = POOL.with(|pool| pool.get()?.run_tests(module));
+  match report {
+    Ok(report) => {
+      println!("{}", report);
+      report.is_success()
+    }
+    Err(e) => {
+      eprintln!("\x1b[31mError\x1b[0m: {} while running {}/testcases", e, module);
+      false
+    }
+  }
+}
 
 // {} — hide from translation
 // [] — hide in tests
@@ -621,3 +806,356 @@ pub fn scan(word: &str, language: &str) {
   insert form even if no value
   template in template
 */
+
+// every library `with_engine` sets up, recording the options each one gets under its name
+#[cfg(test)]
+fn fake_mw(options: std::sync::Arc<std::sync::Mutex<HashMap<String, LuaTable>>>) -> FakeServer {
+  let server = MW_LIBRARIES.iter().fold(FakeServer::new(), |server, name| {
+    let options = options.clone();
+    let name = name.to_string();
+    server.module(&name.clone(), move |lua, _| {
+      let (options, name) = (options.clone(), name.clone());
+      let mut lib = LuaTable::default();
+      let setup = lua.function(move |_, args| {
+        options.lock().unwrap().insert(name.clone(), args.get_table(1).unwrap_or_default());
+        Ok(Default::default())
+      });
+      lib.insert_chunk("setupInterface", setup);
+      lib.insert_chunk("clearLogBuffer", lua.function(|_, _| Ok(Default::default())));
+      let execute = lua.function(|lua, args| {
+        let exports = lua.call(&args.get_function(1).unwrap(), Default::default())?.get_table(1).unwrap_or_default();
+        let mut values = LuaTable::default();
+        values.insert_bool(1, true);
+        values
+          .insert(2, exports.get(args.get_string(2).unwrap()).cloned().unwrap_or_else(|| LuaNull::default().into()));
+        Ok(values)
+      });
+      lib.insert_chunk("executeModule", execute);
+      Ok(std::iter::once((1, lib)).collect())
+    })
+  });
+  server.module("sandbox", move |lua, _| {
+    let options = options.clone();
+    let apply = lua.function(move |_, args| {
+      options.lock().unwrap().insert("sandbox".to_owned(), args.get_table(1).unwrap_or_default());
+      Ok(lua_table![lua_table!["io"], lua_table! {}])
+    });
+    Ok(std::iter::once((1, apply)).collect())
+  })
+}
+#[cfg(test)]
+const MW_LIBRARIES: &[&str] = &[
+  "mw",
+  "mw.frame",
+  "mw.site",
+  "mw.uri",
+  "mw.ustring",
+  "mw.language",
+  "mw.message",
+  "mw.title",
+  "mw.text",
+  "mw.html",
+  "mw.hash",
+];
+
+#[cfg(test)]
+#[test]
+fn test_telua() {
+  use std::sync::{Arc, Mutex};
+
+  let options = Arc::new(Mutex::new(HashMap::new()));
+  let server = fake_mw(options.clone());
+  let server = server.module("bit32", |lua, _| {
+    let main = lua.function(|lua, args| {
+      let who = args.get_table(1).and_then(|it| it.get_table("args")).and_then(|it| it.get_string("who"));
+      lua.call_php("probe", "greet", std::iter::once((1, who.unwrap_or_default())).collect())
+    });
+    let escape = lua.function(|lua, _| {
+      let found = !lua.call_php("mw_interface", "loadPackage", lua_table!["../../../etc/passwd"])?.is_empty();
+      Ok(lua_table![if found { "escaped" } else { "refused" }])
+    });
+    Ok(lua_table![lua_table! { "main" => main, "escape" => escape }])
+  });
+
+  let mut telua = Telua::with_engine(Box::new(server.spawn(vec!["pkg".to_owned()]))).unwrap();
+  {
+    let options = options.lock().unwrap();
+    assert_eq!(options.len(), MW_LIBRARIES.len() + 1);
+    assert_eq!(options["mw.ustring"].get_integer("stringLengthLimit").map(i64::from), Some(2097152));
+    assert_eq!(options["mw"].get_bool("allowEnvFuncs").map(bool::from), Some(false));
+    let title = options["mw.title"].get_table("thisTitle").unwrap();
+    assert_eq!(title.get_string("text"), Some("Sample".into()));
+    assert_eq!(options["sandbox"].get_bool("filesystem").map(bool::from), Some(false));
+    assert!(options["sandbox"].get_table("globals").unwrap().get_bool("loadstring").is_none());
+  }
+
+  telua.machine.insert_callback(
+    "probe-greet",
+    Box::new(|_, args| {
+      let who = args.get_string(1).unwrap_or_default();
+      Ok(std::iter::once((1, LuaString::from(format!("Hello, {}!", who.to_string_lossy())))).collect())
+    }),
+  );
+  telua
+    .machine
+    .register_library("probe", std::iter::once(("greet", LuaString::from("probe-greet"))).collect())
+    .unwrap();
+  let live = telua.machine.live_chunks();
+  let args = std::iter::once(("who".to_owned(), "world".to_owned())).collect();
+  let frame = telua.new_frame(args, None).unwrap();
+  assert_eq!(telua.call("bit32", "main", frame).unwrap(), "Hello, world!");
+  let args = vec![("1", "one"), ("01", "zero one"), ("-1", "minus one")];
+  let args = args.into_iter().map(|(k, v)| (k.to_owned(), v.to_owned())).collect();
+  let frame = telua.new_frame(args, None).unwrap().into_raw().get_table("args").unwrap();
+  assert_eq!(frame.get_string(1), Some("one".into()));
+  assert_eq!(frame.get_string("01"), Some("zero one".into()));
+  assert_eq!(frame.get_string("-1"), Some("minus one".into()));
+  assert_eq!(telua.machine.live_chunks(), live);
+  let frame = telua.new_frame(Default::default(), None).unwrap();
+  assert_eq!(telua.call("bit32", "escape", frame).unwrap(), "refused");
+  telua.reset().unwrap();
+  // welded, there is no other interpreter to go on with
+  assert!(matches!(telua.respawn(), Err(ScribuntoError::Killed)));
+}
+
+// the environment a module gets, the way `pkg/sandbox.lua` and `mw.executeModule` make it out of the
+// options, so the policy is checked without an interpreter
+#[cfg(test)]
+#[test]
+fn test_module_environment() {
+  use std::sync::{Arc, Mutex};
+
+  const BASE: &[&str] = &["io", "os", "dofile", "load", "loadstring", "setfenv", "getfenv", "string", "mw"];
+  let run = |sandbox: Sandbox| -> TeluaResult<String> {
+    let options = Arc::new(Mutex::new(HashMap::new()));
+    let base = Arc::new(Mutex::new(Vec::new()));
+    let (kept, seen) = (base.clone(), options.clone());
+    let server = fake_mw(options)
+      .module("sandbox", move |lua, _| {
+        let kept = kept.clone();
+        let apply = lua.function(move |_, args| {
+          let globals = args.get_table(1).and_then(|it| it.get_table("globals")).unwrap_or_default();
+          let env: Vec<String> =
+            BASE.iter().filter(|it| globals.get_bool(**it).is_some()).map(|it| it.to_string()).collect();
+          let exposed: Vec<String> =
+            env.iter().filter(|it| ["io", "dofile", "load"].contains(&it.as_str())).cloned().collect();
+          *kept.lock().unwrap() = env;
+          Ok(lua_table![Vec::<String>::new(), exposed])
+        });
+        Ok(lua_table![apply])
+      })
+      // a module with a file in `pkg`, whose content the fake has no use for
+      .module("bit32", move |lua, _| {
+        let (base, seen) = (base.clone(), seen.clone());
+        let env = lua.function(move |_, _| {
+          let env_funcs = seen.lock().unwrap()["mw"].get_bool("allowEnvFuncs").map(bool::from).unwrap_or_default();
+          let base = base.lock().unwrap();
+          let env = base.iter().filter(|it| env_funcs || !["setfenv", "getfenv"].contains(&it.as_str()));
+          Ok(lua_table![env.cloned().collect::<Vec<_>>().join(" ")])
+        });
+        Ok(lua_table![lua_table! { "env" => env }])
+      });
+    let mut telua = Telua::with_sandbox(Box::new(server.spawn(vec!["pkg".to_owned()])), sandbox)?;
+    let frame = telua.new_frame(Default::default(), None)?;
+    telua.call("bit32", "env", frame)
+  };
+  assert_eq!(run(Sandbox::default()).unwrap(), "os string mw");
+  assert_eq!(
+    run(Sandbox { loadstring: true, env_funcs: true, ..Sandbox::default() }).unwrap(),
+    "os loadstring setfenv getfenv string mw"
+  );
+  let mut sandbox = Sandbox::default();
+  sandbox.globals.insert("load".to_owned());
+  assert!(matches!(run(sandbox), Err(ScribuntoError::Sandbox(e)) if e == "still reachable: load"));
+}
+
+#[cfg(test)]
+#[test]
+fn test_sandbox_filesystem() {
+  use std::sync::{Arc, Mutex};
+
+  let dir = std::env::temp_dir().join(format!("widictor-filesystem-{}", std::process::id()));
+  std::fs::create_dir_all(&dir).unwrap();
+  std::fs::write(dir.join("outside.lua"), "return {}").unwrap();
+  let outside = dir.join("outside").to_string_lossy().into_owned();
+  let load = |sandbox: Sandbox| {
+    let options = Arc::new(Mutex::new(HashMap::new()));
+    let outside = outside.clone();
+    let server = fake_mw(options.clone()).module("bit32", move |lua, _| {
+      let outside = outside.clone();
+      let load = lua.function(move |lua, _| {
+        let found = !lua.call_php("mw_interface", "loadPackage", lua_table![outside.as_str()])?.is_empty();
+        Ok(lua_table![if found { "loaded" } else { "refused" }])
+      });
+      Ok(lua_table![lua_table! { "load" => load }])
+    });
+    let mut telua = Telua::with_sandbox(Box::new(server.spawn(vec!["pkg".to_owned()])), sandbox).unwrap();
+    let frame = telua.new_frame(Default::default(), None).unwrap();
+    let loaded = telua.call("bit32", "load", frame).unwrap();
+    let filesystem = options.lock().unwrap()["sandbox"].get_bool("filesystem").map(bool::from);
+    (filesystem, loaded)
+  };
+  assert_eq!(load(Sandbox::default()), (Some(false), "refused".to_owned()));
+  assert_eq!(load(Sandbox { filesystem: true, ..Sandbox::default() }), (Some(true), "loaded".to_owned()));
+  std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(test)]
+#[test]
+#[ignore = "needs lua5.1, run with --ignored"]
+fn test_sandbox_escapes() {
+  let dir = std::env::temp_dir().join(format!("widictor-sandbox-{}", std::process::id()));
+  std::fs::create_dir_all(&dir).unwrap();
+  let attempts = [
+    ("io", "return io"),
+    ("os.execute", "return os.execute"),
+    ("os.getenv", "return os.getenv"),
+    ("dofile", "return dofile"),
+    ("loadstring", "return loadstring"),
+    ("string.dump", "return string.dump"),
+    ("debug.getinfo", "return debug.getinfo"),
+    ("setfenv", "return setfenv"),
+    ("getfenv", "return getfenv"),
+    ("package.loadlib", "return package and package.loadlib"),
+    ("string metatable", "return getmetatable('')"),
+    ("global environment", "return rawget(_G, 'mw') and rawget(mw, 'executeModule')"),
+    ("require outside", "local ok, found = pcall(require, '../../../etc/passwd') return ok and found"),
+  ];
+  let mut module = String::from("local p = {}\n");
+  for (id, attempt) in attempts.iter().enumerate() {
+    module += &format!(
+      "p.attempt{} = function() local found = (function() {} end)() return found and 'escaped' or 'safe' end\n",
+      id, attempt.1
+    );
+  }
+  module += "return p\n";
+  std::fs::write(dir.join("escape.lua"), module).unwrap();
+
+  let mut telua = Telua::build(LuaInstance::builder().path(&dir)).unwrap();
+  for (id, attempt) in attempts.iter().enumerate() {
+    let frame = telua.new_frame(Default::default(), None).unwrap();
+    assert_eq!(telua.call("escape", &format!("attempt{}", id), frame).unwrap(), "safe", "{}", attempt.0);
+  }
+
+  let sandbox = Sandbox { loadstring: true, ..Sandbox::default() };
+  let mut telua = Telua::build_sandboxed(LuaInstance::builder().path(&dir), sandbox).unwrap();
+  std::fs::write(
+    dir.join("confined.lua"),
+    "return { run = function() local f = loadstring('return io, os.execute, mw.executeModule') \
+     return (f() or select(2, f()) or select(3, f())) and 'escaped' or 'safe' end }",
+  )
+  .unwrap();
+  let frame = telua.new_frame(Default::default(), None).unwrap();
+  assert_eq!(telua.call("confined", "run", frame).unwrap(), "safe");
+  let _ = std::fs::remove_dir_all(dir);
+}
+
+#[cfg(test)]
+#[test]
+fn test_load_data() {
+  use std::sync::{Arc, Mutex};
+
+  let dir = std::env::temp_dir().join(format!("widictor-load-data-{}", std::process::id()));
+  std::fs::create_dir_all(&dir).unwrap();
+  std::fs::write(dir.join("reader.lua"), "").unwrap();
+  std::fs::write(dir.join("data.lua"), "return { n = 1 }").unwrap();
+  let server = fake_mw(Arc::new(Mutex::new(HashMap::new()))).module("reader", |lua, _| {
+    let version = lua.function(|lua, args| {
+      let module = args.get_table(1).and_then(|it| it.get_table("args")).and_then(|it| it.get_string("module"));
+      let version = lua.call_php("mw_interface", "dataVersion", lua_table![module.unwrap_or_default()])?;
+      Ok(lua_table![version.get_string(1).unwrap_or_else(|| "none".into())])
+    });
+    // a bad argument is an error raised in Lua
+    let bad =
+      lua.function(|lua, _| Ok(lua_table![lua.call_php("mw_interface", "dataVersion", lua_table![true]).err()]));
+    Ok(lua_table![lua_table! { "version" => version, "bad" => bad }])
+  });
+  let mut telua =
+    Telua::with_engine(Box::new(server.spawn(vec!["pkg".to_owned(), dir.to_string_lossy().into()]))).unwrap();
+  let mut version = |module: &str| {
+    let args = std::iter::once(("module".to_owned(), module.to_owned())).collect();
+    let frame = telua.new_frame(args, None).unwrap();
+    telua.call("reader", "version", frame).unwrap()
+  };
+  let first = version("Module:data");
+  assert_eq!(version("Module:data"), first);
+  std::fs::write(dir.join("data.lua"), "return { n = 2 }").unwrap();
+  assert_ne!(version("Module:data"), first);
+  assert_eq!(version("Module:missing"), "none");
+  let frame = telua.new_frame(Default::default(), None).unwrap();
+  assert!(telua.call("reader", "bad", frame).unwrap().starts_with("unexpected value"));
+  let _ = std::fs::remove_dir_all(dir);
+}
+
+// the same table each time, the address in `id` shows it, until the source changes
+#[cfg(test)]
+#[test]
+#[ignore = "needs lua5.1, run with --ignored"]
+fn test_load_data_cache() {
+  let dir = std::env::temp_dir().join(format!("widictor-load-data-cache-{}", std::process::id()));
+  std::fs::create_dir_all(&dir).unwrap();
+  std::fs::write(dir.join("data.lua"), "return { n = 1, id = tostring({}) }").unwrap();
+  std::fs::write(
+    dir.join("reader.lua"),
+    "return { n = function() return mw.loadData('Module:data').n end, \
+     id = function() return mw.loadData('Module:data').id end }",
+  )
+  .unwrap();
+  let mut telua = Telua::build(LuaInstance::builder().path(&dir)).unwrap();
+  let mut call = |function: &str| {
+    let frame = telua.new_frame(Default::default(), None).unwrap();
+    telua.call("reader", function, frame).unwrap()
+  };
+  let id = call("id");
+  assert_eq!(call("n"), "1");
+  assert_eq!(call("id"), id);
+  std::fs::write(dir.join("data.lua"), "return { n = 22, id = tostring({}) }").unwrap();
+  assert_eq!(call("n"), "22");
+  assert_ne!(call("id"), id);
+  let _ = std::fs::remove_dir_all(dir);
+}
+
+#[cfg(test)]
+#[test]
+fn test_parameters() {
+  use std::sync::{Arc, Mutex};
+
+  let server = fake_mw(Arc::new(Mutex::new(HashMap::new())));
+  let mut telua = Telua::with_engine(Box::new(server.spawn(vec!["pkg".to_owned()]))).unwrap();
+  let args = vec![("1", " one "), ("head", "h"), ("empty", "")];
+  let args = args.into_iter().map(|(k, v)| (k.to_owned(), v.to_owned())).collect();
+  let frame = telua.new_frame(args, None).unwrap();
+  let templates = std::env::temp_dir().join(format!("widictor-templates-{}", std::process::id()));
+  std::fs::create_dir_all(&templates).unwrap();
+  std::fs::write(templates.join("pair"), "<{{{1}}}|{{{2|two}}}|{{{name|d}}}>").unwrap();
+  std::fs::write(templates.join("outer"), "{{pair|{{{1}}}|name={{{head|none}}}}}<noinclude>doc</noinclude>").unwrap();
+  let mut subwords = HashSet::new();
+  let mut tags = TagHandlers::builtin();
+  tags.register("poem", |tag, render| tags::content(tag, render).replace('\n', " / "));
+  let mut convert =
+    |src: &str| convert_nodes(&ast::parse_fragment(src), &tags, &templates, &mut subwords, &mut telua, &frame);
+  assert_eq!(convert("[{{{1}}}]"), "[ one ]");
+  assert_eq!(convert("{{{ head }}}"), "h");
+  assert_eq!(convert("{{{2|{{{head|}}}}}}"), "h");
+  assert_eq!(convert("{{{2|{{{3| d }}}}}}"), " d ");
+  assert_eq!(convert("{{{empty|d}}}"), "");
+  assert_eq!(convert("{{{missing}}}|{{{missing|}}}"), "{{{missing}}}|");
+  assert_eq!(convert("{{{{{{head}}}|x}}}"), "x");
+  assert_eq!(convert("<poem>{{{head}}}\n{{{1}}}</poem>"), "h /  one ");
+  // templates read their own arguments, which the caller's frame fills in
+  assert_eq!(convert("{{pair|a|name=n}}"), "<a|two|n>");
+  assert_eq!(convert("{{pair|a|b}}"), "<a|b|d>");
+  assert_eq!(convert("{{outer|x}}"), "<x|two|none>");
+  assert_eq!(convert("{{outer|x|head={{{head}}}}}"), "<x|two|h>");
+  assert_eq!(convert("{{outer|{{{1}}}|head=}}"), "< one |two|>");
+  let nested = frame.nested(vec![("1".to_owned(), "x".to_owned())].into_iter().collect());
+  assert_eq!(nested.arg("1"), Some("x".to_owned()));
+  assert_eq!(
+    nested.into_raw().get_table("parent").and_then(|it| it.get_table("args")).unwrap().get_string("head"),
+    Some("h".into())
+  );
+  // a template that is not there, and a module the fake can make no frame for: it has no `getParentFrame`
+  assert_eq!(convert("a{{widictor-nowhere|{{{head}}}}}b"), "a[[Template:widictor-nowhere]]b");
+  assert_eq!(convert("a{{#invoke:bit32|main}}b"), "ab");
+  std::fs::remove_dir_all(&templates).unwrap();
+}
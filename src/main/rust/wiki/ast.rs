@@ -0,0 +1,368 @@
+#[allow(unused)]
+use crate::wiki as m;
+
+use std::{collections::BTreeMap, fmt::Display};
+
+mod attributes;
+mod block;
+mod inline;
+mod table;
+mod tag;
+
+pub use block::heading_line;
+
+/// A piece of wikitext. Whatever cannot be read as markup, an unclosed `{{` say, is kept as text,
+/// so every page has a tree.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Node {
+  Text(String),
+  /// Runs of lines between blank lines, only on the page level.
+  Paragraph(Vec<Node>),
+  Heading(Heading),
+  List(List),
+  Table(Table),
+  HorizontalRule,
+  Bold(Vec<Node>),
+  Italic(Vec<Node>),
+  Link(Link),
+  ExternalLink(ExternalLink),
+  Template(Template),
+  Parameter(Parameter),
+  Tag(Tag),
+  Comment(String),
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Heading {
+  pub level: u8,
+  pub title: Vec<Node>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListKind {
+  /// `*`
+  Bullet,
+  /// `#`
+  Numbered,
+  /// `;`
+  Term,
+  /// `:`
+  Definition,
+}
+
+/// Consecutive items of the same kind; `#*` lines are a bullet list nested in the numbered item above.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct List {
+  pub kind: ListKind,
+  pub items: Vec<ListItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ListItem {
+  pub content: Vec<Node>,
+  pub nested: Vec<List>,
+}
+
+/// `{| … |}`; a cell may hold another table.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Table {
+  pub attributes: Attributes,
+  /// `|+ caption`, a cell of its own above the rows.
+  pub caption: Option<Cell>,
+  pub rows: Vec<Row>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Row {
+  pub attributes: Attributes,
+  pub cells: Vec<Cell>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Cell {
+  pub header: bool,
+  pub attributes: Attributes,
+  pub content: Vec<Node>,
+}
+
+/// `name="value"` pairs, names in lower case.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct Attributes(pub BTreeMap<String, String>);
+
+/// `[[target|label]]`
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Link {
+  pub target: String,
+  pub label: Option<Vec<Node>>,
+}
+
+/// `[url label]`
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ExternalLink {
+  pub url: String,
+  pub label: Option<Vec<Node>>,
+}
+
+/// `{{name|arg|name=arg}}`, parser functions included: `{{#invoke:m|f}}` is named `#invoke:m`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Template {
+  pub name: Vec<Node>,
+  pub args: Vec<Argument>,
+}
+
+/// A named argument has its name trimmed, a positional one has none.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Argument {
+  pub name: Option<String>,
+  pub value: Vec<Node>,
+}
+
+/// `{{{name|default}}}`
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Parameter {
+  pub name: Vec<Node>,
+  pub default: Option<Vec<Node>>,
+}
+
+/// An HTML tag or a tag of an extension, the name in lower case.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Tag {
+  pub name: String,
+  pub attributes: Attributes,
+  pub body: TagBody,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagBody {
+  /// `<br>`, `<references/>`
+  Empty,
+  Nodes(Vec<Node>),
+  /// Content that is not wikitext, `<nowiki>` or `<math>`.
+  Raw(String),
+}
+
+// how the content at hand is laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+  // a whole page: starts on a new line, lines are grouped in paragraphs
+  Page,
+  // a transcluded page, which is spliced into the text around it
+  Fragment,
+  // the inside of a template or a tag, it starts in the middle of a line
+  Nested,
+}
+
+/// Parses a page.
+pub fn parse(src: &str) -> Vec<Node> {
+  block::blocks(src, &|_| false, Flow::Page).1
+}
+/// Parses a page that is to be transcluded: no paragraphs, its text joins the text around it.
+pub fn parse_fragment(src: &str) -> Vec<Node> {
+  block::blocks(src, &|_| false, Flow::Fragment).1
+}
+
+/// The text a reader would see, markup left out; templates and comments give nothing.
+pub fn plain_text(nodes: &[Node]) -> String {
+  let mut out = String::new();
+  for node in nodes {
+    match node {
+      Node::Text(text) => out += text,
+      Node::Paragraph(children) | Node::Bold(children) | Node::Italic(children) => out += &plain_text(children),
+      Node::Heading(heading) => out += &plain_text(&heading.title),
+      Node::Link(Link { label: Some(label), .. }) | Node::ExternalLink(ExternalLink { label: Some(label), .. }) => {
+        out += &plain_text(label)
+      }
+      Node::Link(Link { target, .. }) => out += target,
+      Node::ExternalLink(ExternalLink { url, .. }) => out += url,
+      Node::Tag(Tag { body: TagBody::Nodes(children), .. }) => out += &plain_text(children),
+      Node::Tag(Tag { body: TagBody::Raw(raw), .. }) => out += raw,
+      Node::List(_)
+      | Node::Table(_)
+      | Node::HorizontalRule
+      | Node::Template(_)
+      | Node::Parameter(_)
+      | Node::Tag(_)
+      | Node::Comment(_) => {}
+    }
+  }
+  out
+}
+
+impl Attributes {
+  pub fn get(&self, name: &str) -> Option<&str> {
+    self.0.get(name).map(String::as_str)
+  }
+  // a count such as `rowspan`, read as browsers do: the digits it starts with
+  fn count(&self, name: &str) -> Option<usize> {
+    let value = self.get(name)?.trim();
+    let end = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    value[..end].parse().ok()
+  }
+}
+
+impl Cell {
+  /// How many rows and columns the cell covers; `rowspan=0` is all the rows left, as in HTML.
+  pub fn span(&self) -> (Option<usize>, usize) {
+    let rows = match self.attributes.count("rowspan") {
+      Some(0) => None,
+      rows => Some(rows.unwrap_or(1)),
+    };
+    // MediaWiki caps spans at these
+    (rows.map(|it| it.min(65534)), self.attributes.count("colspan").unwrap_or(1).clamp(1, 1000))
+  }
+}
+
+impl Table {
+  /// The cells laid out the way a browser would, a row of the grid for each row of the table: a cell
+  /// that spans shows up in every place it covers. Places no cell covers are `None`.
+  pub fn grid(&self) -> Vec<Vec<Option<&Cell>>> {
+    let height = self.rows.len();
+    let mut grid: Vec<Vec<Option<&Cell>>> = vec![Vec::new(); height];
+    for (y, row) in self.rows.iter().enumerate() {
+      let mut x = 0;
+      for cell in &row.cells {
+        // places taken by the cells of the rows above
+        while grid[y].get(x).is_some_and(Option::is_some) {
+          x += 1;
+        }
+        let (rows, columns) = cell.span();
+        let bottom = rows.map_or(height, |it| (y + it).min(height));
+        for line in &mut grid[y..bottom] {
+          if line.len() < x + columns {
+            line.resize(x + columns, None);
+          }
+          line[x..x + columns].iter_mut().for_each(|it| *it = Some(cell));
+        }
+        x += columns;
+      }
+    }
+    let width = grid.iter().map(Vec::len).max().unwrap_or(0);
+    grid.iter_mut().for_each(|it| it.resize(width, None));
+    grid
+  }
+}
+
+// appends to the text node at the end, if there is one
+fn push_text(out: &mut Vec<Node>, text: &str) {
+  if text.is_empty() {
+    return;
+  }
+  match out.last_mut() {
+    Some(Node::Text(last)) => *last += text,
+    _ => out.push(Node::Text(text.to_owned())),
+  }
+}
+fn extend_text(out: &mut Vec<Node>, nodes: Vec<Node>) {
+  for node in nodes {
+    match node {
+      Node::Text(text) => push_text(out, &text),
+      node => out.push(node),
+    }
+  }
+}
+
+impl Display for Heading {
+  /// The heading as a line of plain wikitext, `==Latin==`.
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let marks = "=".repeat(self.level as usize);
+    write!(f, "{}{}{}", marks, plain_text(&self.title), marks)
+  }
+}
+
+#[cfg(test)]
+fn text(text: &str) -> Node {
+  Node::Text(text.to_owned())
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse() {
+  let page = "==Latin==\n\
+    ===Noun===\n\
+    {{la-noun|rosa<.1>\n|g=f}}\n\
+    \n\
+    # [[rose]], ''a '''red''' flower''\n\
+    #* {{quote|la|x}} <!-- note -->\n\
+    ----\n\
+    See [https://la.wikipedia.org Vicipaedia] and {{{1|none}}}<ref name=\"a\">[[x|y]]</ref><nowiki>[[no]]</nowiki>\n";
+  let nodes = parse(page);
+  assert_eq!(nodes[0], Node::Heading(Heading { level: 2, title: vec![text("Latin")] }));
+  assert_eq!(nodes[1], Node::Heading(Heading { level: 3, title: vec![text("Noun")] }));
+  let template = Template {
+    name: vec![text("la-noun")],
+    args: vec![
+      Argument { name: None, value: vec![text("rosa<.1>\n")] },
+      Argument { name: Some("g".to_owned()), value: vec![text("f")] },
+    ],
+  };
+  assert_eq!(nodes[2], Node::Paragraph(vec![Node::Template(template)]));
+  let definition = vec![
+    text(" "),
+    Node::Link(Link { target: "rose".to_owned(), label: None }),
+    text(", "),
+    Node::Italic(vec![text("a "), Node::Bold(vec![text("red")]), text(" flower")]),
+  ];
+  let positional = |value: &str| Argument { name: None, value: vec![text(value)] };
+  let quote = Template { name: vec![text("quote")], args: vec![positional("la"), positional("x")] };
+  let list = List {
+    kind: ListKind::Numbered,
+    items: vec![ListItem {
+      content: definition,
+      nested: vec![List {
+        kind: ListKind::Bullet,
+        items: vec![ListItem {
+          content: vec![text(" "), Node::Template(quote), text(" "), Node::Comment(" note ".to_owned())],
+          nested: vec![],
+        }],
+      }],
+    }],
+  };
+  assert_eq!(nodes[3], Node::List(list));
+  assert_eq!(nodes[4], Node::HorizontalRule);
+  let paragraph = vec![
+    text("See "),
+    Node::ExternalLink(ExternalLink {
+      url: "https://la.wikipedia.org".to_owned(),
+      label: Some(vec![text("Vicipaedia")]),
+    }),
+    text(" and "),
+    Node::Parameter(Parameter { name: vec![text("1")], default: Some(vec![text("none")]) }),
+    Node::Tag(Tag {
+      name: "ref".to_owned(),
+      attributes: attributes::attributes("name=\"a\""),
+      body: TagBody::Nodes(vec![Node::Link(Link { target: "x".to_owned(), label: Some(vec![text("y")]) })]),
+    }),
+    Node::Tag(Tag {
+      name: "nowiki".to_owned(),
+      attributes: Default::default(),
+      body: TagBody::Raw("[[no]]".to_owned()),
+    }),
+  ];
+  assert_eq!(nodes[5], Node::Paragraph(paragraph));
+  assert_eq!(nodes.len(), 6);
+  assert_eq!(plain_text(&nodes[5..]), "See Vicipaedia and y[[no]]");
+
+  // what is not closed stays text
+  assert_eq!(parse("{{a|b [[c"), vec![Node::Paragraph(vec![text("{{a|b [[c")])]);
+  assert_eq!(parse_fragment("a\n\nb"), vec![text("a\n\nb")]);
+  let link = Node::Link(Link { target: "c".to_owned(), label: None });
+  assert_eq!(parse_fragment("a <ref>b [[c]]"), vec![text("a <ref>b "), link]);
+  let parameter = Node::Parameter(Parameter { name: vec![text("1")], default: None });
+  assert_eq!(parse_fragment("{{{{{1}}}}}"), vec![Node::Template(Template { name: vec![parameter], args: vec![] })]);
+}
+
+#[cfg(test)]
+#[test]
+fn test_serialize() {
+  let nodes = parse("== Noun ==\n{{head|la|g=m}}");
+  let json = serde_json::to_string(&nodes).unwrap();
+  assert_eq!(
+    json,
+    r#"[{"type":"heading","value":{"level":2,"title":[{"type":"text","value":"Noun"}]}},{"type":"paragraph","value":[{"type":"template","value":{"name":[{"type":"text","value":"head"}],"args":[{"name":null,"value":[{"type":"text","value":"la"}]},{"name":"g","value":[{"type":"text","value":"m"}]}]}}]}]"#
+  );
+  assert_eq!(serde_json::from_str::<Vec<Node>>(&json).unwrap(), nodes);
+}
@@ -0,0 +1,53 @@
+#[allow(unused)]
+use crate::wiki as m;
+use m::ast::Attributes;
+
+// what ends a name or a value without quotes
+fn delimiter(c: char) -> bool {
+  c.is_whitespace() || c == '=' || c == '"' || c == '\''
+}
+
+/// `class="wikitable" style='x' rowspan=2 hidden`, the way MediaWiki reads them: names in lower case,
+/// values with or without quotes, a name alone has an empty value and the last of a name wins.
+/// Whatever is not an attribute is left out.
+pub(super) fn attributes(mut src: &str) -> Attributes {
+  let mut out = Attributes::default();
+  loop {
+    src = src.trim_start_matches(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == '=');
+    let size = src.find(delimiter).unwrap_or(src.len());
+    if size == 0 {
+      return out;
+    }
+    let name = src[..size].to_lowercase();
+    let tail = src[size..].trim_start();
+    let (value, tail) = match tail.strip_prefix('=').map(str::trim_start) {
+      Some(tail) => match tail.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+          let tail = &tail[1..];
+          // one never closed runs to the end
+          let end = tail.find(quote).unwrap_or(tail.len());
+          (&tail[..end], tail.get(end + 1..).unwrap_or_default())
+        }
+        _ => {
+          let end = tail.find(char::is_whitespace).unwrap_or(tail.len());
+          (&tail[..end], &tail[end..])
+        }
+      },
+      None => ("", tail),
+    };
+    out.0.insert(name, value.to_owned());
+    src = tail;
+  }
+}
+
+#[cfg(test)]
+#[test]
+fn test_attributes() {
+  let parsed = attributes(" class=\"wikitable sortable\" STYLE = 'width: 5em' rowspan=2 hidden colspan=\"3");
+  let expected =
+    [("class", "wikitable sortable"), ("style", "width: 5em"), ("rowspan", "2"), ("hidden", ""), ("colspan", "3")];
+  assert_eq!(parsed.0.len(), expected.len());
+  expected.iter().for_each(|(name, value)| assert_eq!(parsed.get(name), Some(*value), "{}", name));
+  assert_eq!(attributes("a=1 a=2").get("a"), Some("2"));
+  assert_eq!(attributes("").0.len(), 0);
+}
@@ -0,0 +1,240 @@
+#[allow(unused)]
+use crate::wiki as m;
+use m::{
+  ast::{extend_text, inline::inline, push_text, table, Flow, Heading, List, ListItem, ListKind, Node},
+  wiki_error::WikiError,
+};
+
+use nom::IResult;
+
+/// Lines up to where `stop` says: headings, lists, tables and rules where a line starts, inline
+/// content in between.
+pub(super) fn blocks<'a>(mut src: &'a str, stop: &dyn Fn(&str) -> bool, flow: Flow) -> (&'a str, Vec<Node>) {
+  let mut out = Vec::new();
+  let mut paragraph = Vec::new();
+  let mut line_start = flow != Flow::Nested;
+  while !src.is_empty() && !stop(src) {
+    if line_start {
+      if let Ok((tail, node)) = block(src, stop) {
+        end_paragraph(&mut out, &mut paragraph);
+        out.push(node);
+        // blocks end where their last line does, the line break is left for `stop` to see
+        src = tail;
+        line_start = false;
+        if let Some(tail) = src.strip_prefix('\n').filter(|_| !stop(src)) {
+          src = tail;
+          line_start = true;
+        }
+        continue;
+      }
+      if flow == Flow::Page {
+        let end = src.find('\n').map_or(src.len(), |it| it + 1);
+        if src[..end].trim().is_empty() {
+          end_paragraph(&mut out, &mut paragraph);
+          src = &src[end..];
+          continue;
+        }
+      }
+    }
+    let (tail, nodes) = inline(src, stop, true);
+    let target = if flow == Flow::Page { &mut paragraph } else { &mut out };
+    extend_text(target, nodes);
+    src = tail;
+    line_start = false;
+    if let Some(tail) = src.strip_prefix('\n').filter(|_| !stop(src)) {
+      push_text(target, "\n");
+      src = tail;
+      line_start = true;
+    }
+  }
+  end_paragraph(&mut out, &mut paragraph);
+  (src, out)
+}
+
+// the line break a paragraph ends with is not part of it
+fn end_paragraph(out: &mut Vec<Node>, paragraph: &mut Vec<Node>) {
+  if let Some(Node::Text(text)) = paragraph.last_mut() {
+    if text.ends_with('\n') {
+      text.pop();
+      if text.is_empty() {
+        paragraph.pop();
+      }
+    }
+  }
+  if !paragraph.is_empty() {
+    out.push(Node::Paragraph(std::mem::take(paragraph)));
+  }
+}
+
+fn block<'a>(src: &'a str, stop: &dyn Fn(&str) -> bool) -> IResult<&'a str, Node, WikiError<&'a str>> {
+  match src.chars().next() {
+    Some('=') => heading(src, stop),
+    Some('-') => rule(src),
+    Some('*') | Some('#') | Some(':') | Some(';') => list(src, stop),
+    _ => table::table(src),
+  }
+}
+
+// the `=` that end a heading, when nothing but blanks follow them on the line
+fn closing(src: &str) -> Option<usize> {
+  let marks = src.bytes().take_while(|&it| it == b'=').count();
+  let rest = src[marks..].trim_start_matches([' ', '\t']);
+  if marks > 0 && (rest.is_empty() || rest.starts_with('\n')) {
+    Some(marks)
+  } else {
+    None
+  }
+}
+fn heading<'a>(src: &'a str, stop: &dyn Fn(&str) -> bool) -> IResult<&'a str, Node, WikiError<&'a str>> {
+  let open = src.bytes().take_while(|&it| it == b'=').count();
+  let (tail, title) = inline(&src[open..], &|s| stop(s) || closing(s).is_some(), true);
+  let close = closing(tail).ok_or(WikiError::OpenNotMatchesClose)?;
+  // `===a==` is `=a` on the second level
+  let level = open.min(close).min(6);
+  let mut marked = vec![Node::Text("=".repeat(open - level))];
+  extend_text(&mut marked, title);
+  push_text(&mut marked, &"=".repeat(close - level));
+  trim(&mut marked);
+  let tail = &tail[close..];
+  let tail = tail.find('\n').map_or("", |it| &tail[it..]);
+  Ok((tail, Node::Heading(Heading { level: level as u8, title: marked })))
+}
+/// A heading on a line of its own, `=== Etymology 2 ===`: its level, 1 to 6, and its title without
+/// the blanks around it. Marks that are not matched stay in the title, `===a==` is `=a` on the second
+/// level, the same as in a parsed page.
+pub fn heading_line(src: &str) -> IResult<&str, (u8, &str), WikiError<&str>> {
+  let (line, tail) = match src.find('\n') {
+    Some(end) => (&src[..end], &src[end + 1..]),
+    None => (src, ""),
+  };
+  let line = line.trim_end();
+  let open = line.bytes().take_while(|&it| it == b'=').count();
+  let close = line.bytes().rev().take_while(|&it| it == b'=').count();
+  let level = open.min(close).min(6);
+  if level == 0 || open == line.len() {
+    return Err(WikiError::Nom(src, nom::error::ErrorKind::Tag).into());
+  }
+  Ok((tail, (level as u8, line[level..line.len() - level].trim())))
+}
+// blanks around a title are not part of it
+fn trim(nodes: &mut Vec<Node>) {
+  if let Some(Node::Text(text)) = nodes.first_mut() {
+    *text = text.trim_start().to_owned();
+  }
+  if let Some(Node::Text(text)) = nodes.last_mut() {
+    *text = text.trim_end().to_owned();
+  }
+  nodes.retain(|it| !matches!(it, Node::Text(text) if text.is_empty()));
+}
+
+fn rule(src: &str) -> IResult<&str, Node, WikiError<&str>> {
+  let dashes = src.bytes().take_while(|&it| it == b'-').count();
+  if dashes < 4 {
+    return Err(WikiError::Nom(src, nom::error::ErrorKind::Tag).into());
+  }
+  // what follows on the same line is ordinary text
+  let tail = &src[dashes..];
+  let tail = match tail.find('\n') {
+    Some(end) if tail[..end].trim().is_empty() => &tail[end..],
+    None if tail.trim().is_empty() => "",
+    _ => tail,
+  };
+  Ok((tail, Node::HorizontalRule))
+}
+
+struct Line {
+  prefix: Vec<ListKind>,
+  content: Vec<Node>,
+}
+fn list<'a>(mut src: &'a str, stop: &dyn Fn(&str) -> bool) -> IResult<&'a str, Node, WikiError<&'a str>> {
+  let kind = |c| match c {
+    '*' => Some(ListKind::Bullet),
+    '#' => Some(ListKind::Numbered),
+    ';' => Some(ListKind::Term),
+    ':' => Some(ListKind::Definition),
+    _ => None,
+  };
+  let mut lines: Vec<Line> = Vec::new();
+  loop {
+    let prefix: Vec<ListKind> = src.chars().map_while(kind).collect();
+    if prefix.is_empty() {
+      break;
+    }
+    let (tail, content) = inline(&src[prefix.len()..], stop, true);
+    lines.push(Line { prefix, content });
+    src = tail;
+    // a change of kind on the first level starts another list
+    match tail.strip_prefix('\n') {
+      Some(next) if !stop(tail) && next.chars().next().and_then(kind) == Some(lines[0].prefix[0]) => src = next,
+      _ => break,
+    }
+  }
+  let list = nest(&mut lines, 0).pop().ok_or(WikiError::Nom(src, nom::error::ErrorKind::Char))?;
+  Ok((src, Node::List(list)))
+}
+// the lines all share their prefix up to `depth`
+fn nest(lines: &mut [Line], depth: usize) -> Vec<List> {
+  let mut lists: Vec<List> = Vec::new();
+  let mut id = 0;
+  while id < lines.len() {
+    let kind = lines[id].prefix[depth];
+    // `#*` with no `#` line before it gets an empty item to hang from
+    let content = if lines[id].prefix.len() == depth + 1 {
+      id += 1;
+      std::mem::take(&mut lines[id - 1].content)
+    } else {
+      Vec::new()
+    };
+    let start = id;
+    while id < lines.len() && lines[id].prefix.len() > depth + 1 && lines[id].prefix[depth] == kind {
+      id += 1;
+    }
+    let item = ListItem { content, nested: nest(&mut lines[start..id], depth + 1) };
+    match lists.last_mut() {
+      Some(list) if list.kind == kind => list.items.push(item),
+      _ => lists.push(List { kind, items: vec![item] }),
+    }
+  }
+  lists
+}
+
+#[cfg(test)]
+#[test]
+fn test_list() {
+  use m::ast::parse;
+
+  let text = |it: &str| Node::Text(it.to_owned());
+  let item = |it: &str, nested: Vec<List>| ListItem { content: vec![text(it)], nested };
+  let nodes = parse("# a\n#: b\n#:* c\n# d\n#*: e\n* f\n\n=== x ===");
+  let e = List { kind: ListKind::Definition, items: vec![item(" e", vec![])] };
+  let numbered = List {
+    kind: ListKind::Numbered,
+    items: vec![
+      item(
+        " a",
+        vec![List {
+          kind: ListKind::Definition,
+          items: vec![item(" b", vec![List { kind: ListKind::Bullet, items: vec![item(" c", vec![])] }])],
+        }],
+      ),
+      item(" d", vec![List { kind: ListKind::Bullet, items: vec![ListItem { content: vec![], nested: vec![e] }] }]),
+    ],
+  };
+  assert_eq!(nodes[0], Node::List(numbered));
+  assert_eq!(nodes[1], Node::List(List { kind: ListKind::Bullet, items: vec![item(" f", vec![])] }));
+  assert_eq!(nodes[2], Node::Heading(Heading { level: 3, title: vec![text("x")] }));
+
+  let nodes = parse("= a =\n==b=\n=== c = d ===  \n== ==\n");
+  let heading = |level, title: &str| Node::Heading(Heading { level, title: vec![text(title)] });
+  assert_eq!(nodes[..3], [heading(1, "a"), heading(1, "=b"), heading(3, "c = d")]);
+  assert_eq!(nodes[3], Node::Heading(Heading { level: 2, title: vec![] }));
+  assert_eq!(parse("====\n"), vec![Node::Paragraph(vec![text("====")])]);
+
+  assert_eq!(heading_line("==Proto-Indo-European==\nx").ok(), Some(("x", (2, "Proto-Indo-European"))));
+  assert_eq!(heading_line("== Ancient Greek ==  ").ok(), Some(("", (2, "Ancient Greek"))));
+  assert_eq!(heading_line("======= Noun =======").ok(), Some(("", (6, "= Noun ="))));
+  assert_eq!(heading_line("===Noun====").ok(), Some(("", (3, "Noun="))));
+  assert!(heading_line("Noun").is_err());
+  assert!(heading_line("===").is_err());
+  assert!(heading_line(" ==Noun==").is_err());
+}
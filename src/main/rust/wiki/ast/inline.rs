@@ -0,0 +1,158 @@
+#[allow(unused)]
+use crate::wiki as m;
+use m::{
+  ast::{block, push_text, tag, Argument, ExternalLink, Flow, Link, Node, Parameter, Template},
+  wiki_error::WikiError,
+};
+
+use nom::{bytes::complete::tag, IResult};
+
+// what may follow `[` for it to be an external link
+const SCHEMES: &[&str] = &["http://", "https://", "ftp://", "ftps://", "//", "mailto:", "irc://", "ircs://", "news:"];
+
+/// Content up to where `stop` says, or up to the end of the line if `line` is set. Constructs that
+/// started before the end of the line go on past it, a template or a `<ref>` say.
+pub(super) fn inline<'a>(mut src: &'a str, stop: &dyn Fn(&str) -> bool, line: bool) -> (&'a str, Vec<Node>) {
+  let mut out = Vec::new();
+  while !(src.is_empty() || stop(src) || line && src.starts_with('\n')) {
+    match markup(src, stop) {
+      Ok((tail, node)) => {
+        match node {
+          Node::Text(text) => push_text(&mut out, &text),
+          node => out.push(node),
+        }
+        src = tail;
+      }
+      Err(_) => {
+        let size = src.chars().next().map_or(1, char::len_utf8);
+        push_text(&mut out, &src[..size]);
+        src = &src[size..];
+      }
+    }
+  }
+  (src, out)
+}
+
+// a construct starting right here
+fn markup<'a>(src: &'a str, stop: &dyn Fn(&str) -> bool) -> IResult<&'a str, Node, WikiError<&'a str>> {
+  if src.starts_with("<!--") {
+    comment(src)
+  } else if src.starts_with("{{{{{") && !src.starts_with("{{{{{{") {
+    // `{{{{{1}}}}}` is the template the parameter names, `{{{{{{1}}}}}}` the parameter
+    template(src).or_else(|_| parameter(src))
+  } else if src.starts_with("{{{") {
+    parameter(src).or_else(|_| template(src))
+  } else if src.starts_with("{{") {
+    template(src)
+  } else if src.starts_with("[[") {
+    link(src)
+  } else if src.starts_with('[') {
+    external_link(src)
+  } else if src.starts_with("''") {
+    formatting(src, stop)
+  } else if src.starts_with('<') {
+    tag::element(src, stop)
+  } else {
+    Err(WikiError::Nom(src, nom::error::ErrorKind::Char).into())
+  }
+}
+
+fn comment(src: &str) -> IResult<&str, Node, WikiError<&str>> {
+  let (src, _) = tag("<!--")(src)?;
+  // one that is never closed runs to the end, as MediaWiki has it
+  let end = src.find("-->").unwrap_or(src.len());
+  Ok((src.get(end + 3..).unwrap_or_default(), Node::Comment(src[..end].to_owned())))
+}
+
+fn template(src: &str) -> IResult<&str, Node, WikiError<&str>> {
+  let (src, _) = tag("{{")(src)?;
+  let (mut src, name) = inline(src, &|s| s.starts_with('|') || s.starts_with("}}"), false);
+  let mut args = Vec::new();
+  loop {
+    if let Some(tail) = src.strip_prefix("}}") {
+      return Ok((tail, Node::Template(Template { name, args })));
+    }
+    let (tail, _) = tag("|")(src).map_err(|_: nom::Err<WikiError<&str>>| WikiError::BadTemplate)?;
+    let (tail, arg) = argument(tail);
+    args.push(arg);
+    src = tail;
+  }
+}
+
+// `name=value` when there is an `=` ahead of any markup, the value alone otherwise
+fn argument(src: &str) -> (&str, Argument) {
+  let (name, src) = match src.find(['=', '|', '{', '}', '[', ']', '<']) {
+    Some(end) if src[end..].starts_with('=') => (Some(src[..end].trim().to_owned()), &src[end + 1..]),
+    _ => (None, src),
+  };
+  let (src, value) = block::blocks(src, &|s| s.starts_with('|') || s.starts_with("}}"), Flow::Nested);
+  (src, Argument { name, value })
+}
+
+fn parameter(src: &str) -> IResult<&str, Node, WikiError<&str>> {
+  let (src, _) = tag("{{{")(src)?;
+  let end = |s: &str| s.starts_with('|') || s.starts_with("}}}");
+  let (mut src, name) = inline(src, &end, false);
+  let mut default = None;
+  // only the first alternative counts, `{{{a|b|c}}}` is `b` when `a` is missing
+  while let Some(tail) = src.strip_prefix('|') {
+    let (tail, value) = block::blocks(tail, &end, Flow::Nested);
+    default = default.or(Some(value));
+    src = tail;
+  }
+  let (src, _) = tag("}}}")(src)?;
+  Ok((src, Node::Parameter(Parameter { name, default })))
+}
+
+fn link(src: &str) -> IResult<&str, Node, WikiError<&str>> {
+  let (src, _) = tag("[[")(src)?;
+  let end = src.find(['|', ']', '[', '{', '}', '<', '\n']).unwrap_or(src.len());
+  let (target, src) = src.split_at(end);
+  let (src, label) = match src.strip_prefix('|') {
+    Some(src) => {
+      let (src, label) = inline(src, &|s| s.starts_with("]]"), true);
+      (src, Some(label))
+    }
+    None => (src, None),
+  };
+  let (src, _) = tag("]]")(src).map_err(|_: nom::Err<WikiError<&str>>| WikiError::OpenNotMatchesClose)?;
+  Ok((src, Node::Link(Link { target: target.trim().to_owned(), label })))
+}
+
+fn external_link(src: &str) -> IResult<&str, Node, WikiError<&str>> {
+  let (src, _) = tag("[")(src)?;
+  if !SCHEMES.iter().any(|it| src.len() >= it.len() && src[..it.len()].eq_ignore_ascii_case(it)) {
+    return Err(WikiError::Nom(src, nom::error::ErrorKind::Tag).into());
+  }
+  let end = src.find(|c: char| c.is_whitespace() || matches!(c, ']' | '[' | '<' | '"')).unwrap_or(src.len());
+  let (url, src) = src.split_at(end);
+  let src = src.trim_start_matches([' ', '\t']);
+  let (src, label) = inline(src, &|s| s.starts_with(']'), true);
+  let (src, _) = tag("]")(src).map_err(|_: nom::Err<WikiError<&str>>| WikiError::OpenNotMatchesClose)?;
+  let label = if label.is_empty() { None } else { Some(label) };
+  Ok((src, Node::ExternalLink(ExternalLink { url: url.to_owned(), label })))
+}
+
+fn quotes(src: &str) -> usize {
+  src.bytes().take_while(|&it| it == b'\'').count()
+}
+// `''` closes italics and `'''` bold, five close both; neither goes past the end of the line
+fn formatting<'a>(src: &'a str, stop: &dyn Fn(&str) -> bool) -> IResult<&'a str, Node, WikiError<&'a str>> {
+  let run = quotes(src);
+  match run {
+    4 => return Ok((&src[1..], Node::Text("'".to_owned()))),
+    run if run > 5 => return Ok((&src[run - 5..], Node::Text("'".repeat(run - 5)))),
+    _ => {}
+  }
+  // `'''''` opens italics here and bold right inside
+  let bold = run == 3;
+  let size = if bold { 3 } else { 2 };
+  let closes = move |s: &str| match quotes(s) {
+    3 => bold,
+    2 => !bold,
+    run => run >= 5,
+  };
+  let (src, children) = inline(&src[size..], &|s| stop(s) || closes(s), true);
+  let src = if closes(src) { &src[size..] } else { src };
+  Ok((src, if bold { Node::Bold(children) } else { Node::Italic(children) }))
+}
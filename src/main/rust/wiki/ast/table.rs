@@ -0,0 +1,165 @@
+#[allow(unused)]
+use crate::wiki as m;
+use m::{
+  ast::{attributes::attributes, block::blocks, inline::inline, Cell, Flow, Node, Row, Table},
+  wiki_error::WikiError,
+};
+
+use nom::{bytes::complete::tag, IResult};
+
+// blanks a table line may start with
+fn indent(src: &str) -> &str {
+  src.trim_start_matches([' ', '\t'])
+}
+// the rest of the line and what comes after it
+fn line(src: &str) -> (&str, &str) {
+  match src.find('\n') {
+    Some(end) => (&src[..end], &src[end + 1..]),
+    None => (src, ""),
+  }
+}
+// a line of the table itself rather than of the content of a cell
+fn table_line(src: &str) -> bool {
+  let src = indent(src);
+  src.starts_with('|') || src.starts_with('!')
+}
+
+/// `{| … |}`, on a line of its own. One that is never closed ends with the page.
+pub(super) fn table(src: &str) -> IResult<&str, Node, WikiError<&str>> {
+  let (src, _) = tag("{|")(indent(src))?;
+  let (head, mut src) = line(src);
+  let mut table = Table { attributes: attributes(head), caption: None, rows: Vec::new() };
+  let mut row = Row::default();
+  while !src.is_empty() {
+    let current = indent(src);
+    if let Some(tail) = current.strip_prefix("|}") {
+      src = &tail[line(tail).0.len()..];
+      break;
+    } else if let Some(tail) = current.strip_prefix("|+") {
+      let (tail, caption) = cell(tail, false);
+      table.caption = Some(caption);
+      src = tail.strip_prefix('\n').unwrap_or(tail);
+    } else if let Some(tail) = current.strip_prefix("|-") {
+      let (row_attributes, tail) = line(tail);
+      if !row.cells.is_empty() || !row.attributes.0.is_empty() {
+        table.rows.push(std::mem::take(&mut row));
+      }
+      row.attributes = attributes(row_attributes.trim_start_matches('-'));
+      src = tail;
+    } else if current.starts_with('|') || current.starts_with('!') {
+      let header = current.starts_with('!');
+      let mut tail = &current[1..];
+      loop {
+        let (rest, cell) = cell(tail, header);
+        row.cells.push(cell);
+        match rest.strip_prefix("||").or_else(|| if header { rest.strip_prefix("!!") } else { None }) {
+          Some(rest) => tail = rest,
+          None => {
+            tail = rest;
+            break;
+          }
+        }
+      }
+      src = tail.strip_prefix('\n').unwrap_or(tail);
+    } else {
+      // text between the rows is left out of the table, MediaWiki shows it before it
+      src = line(src).1;
+    }
+  }
+  if !row.cells.is_empty() || !row.attributes.0.is_empty() {
+    table.rows.push(row);
+  }
+  Ok((src, Node::Table(table)))
+}
+
+// `attributes | content`, the content running to the next line of the table
+fn cell(src: &str, header: bool) -> (&str, Cell) {
+  let next = |s: &str| s.starts_with("||") || (header && s.starts_with("!!"));
+  let (rest, _) = inline(src, &|s| next(s) || s.starts_with('|'), true);
+  let (attributes, src) = match rest.strip_prefix('|') {
+    Some(tail) if !next(rest) => (attributes(&src[..src.len() - rest.len()]), tail),
+    _ => (Default::default(), src),
+  };
+  let (src, content) =
+    blocks(src, &|s| next(s) || (s.starts_with('\n') && table_line(&s[1..])) || s == "\n", Flow::Nested);
+  (src, Cell { header, attributes, content })
+}
+
+#[cfg(test)]
+#[test]
+fn test_table() {
+  use m::ast::{parse, List, ListItem, ListKind};
+
+  let text = |it: &str| vec![Node::Text(it.to_owned())];
+  let cell =
+    |header, written: &str, content: &str| Cell { header, attributes: attributes(written), content: text(content) };
+  let nodes = parse(
+    "{| class=\"wikitable\"\n\
+     |+ style=\"x\" | Forms\n\
+     ! case !! singular\n\
+     |- style=\"x\"\n\
+     | nominative || style=\"y\" | rosa\n\
+     |-\n\
+     | list\n\
+     * a\n\
+     |}\n\
+     after",
+  );
+  let table = Table {
+    attributes: attributes("class=\"wikitable\""),
+    caption: Some(cell(false, "style=\"x\"", " Forms")),
+    rows: vec![
+      Row { attributes: Default::default(), cells: vec![cell(true, "", " case "), cell(true, "", " singular")] },
+      Row {
+        attributes: attributes("style=\"x\""),
+        cells: vec![cell(false, "", " nominative "), cell(false, "style=\"y\"", " rosa")],
+      },
+      Row {
+        attributes: Default::default(),
+        cells: vec![Cell {
+          header: false,
+          attributes: Default::default(),
+          content: vec![
+            Node::Text(" list\n".to_owned()),
+            Node::List(List { kind: ListKind::Bullet, items: vec![ListItem { content: text(" a"), nested: vec![] }] }),
+          ],
+        }],
+      },
+    ],
+  };
+  assert_eq!(nodes, vec![Node::Table(table), Node::Paragraph(text("after"))]);
+
+  // spans, and a table in a cell
+  let nodes = parse(
+    "{|\n\
+     ! rowspan=\"2\" | a !! colspan=2 | b\n\
+     |-\n\
+     | c || d\n\
+     |-\n\
+     | e ||\n\
+     {| class=\"inner\"\n\
+     | f\n\
+     |}\n\
+     |}",
+  );
+  let table = match &nodes[..] {
+    [Node::Table(table)] => table,
+    nodes => panic!("{:?}", nodes),
+  };
+  let names = |grid: Vec<Vec<Option<&Cell>>>| -> Vec<Vec<String>> {
+    let name = |cell: &Cell| match &cell.content[..] {
+      [Node::Text(text)] => text.trim().to_owned(),
+      [Node::Text(_), Node::Table(_)] => "table".to_owned(),
+      content => format!("{:?}", content),
+    };
+    grid.into_iter().map(|row| row.into_iter().map(|it| it.map_or("-".to_owned(), name)).collect()).collect()
+  };
+  assert_eq!(names(table.grid()), vec![vec!["a", "b", "b"], vec!["a", "c", "d"], vec!["e", "table", "-"]]);
+  let inner = match &table.rows[2].cells[1].content[1] {
+    Node::Table(inner) => inner,
+    node => panic!("{:?}", node),
+  };
+  assert_eq!(inner.attributes.get("class"), Some("inner"));
+  assert_eq!(names(inner.grid()), vec![vec!["f"]]);
+  assert_eq!(table.rows[0].cells[1].span(), (Some(1), 2));
+}
@@ -0,0 +1,146 @@
+#[allow(unused)]
+use crate::wiki as m;
+use m::{
+  ast::{attributes::attributes, block::blocks, Flow, Node, Tag, TagBody},
+  wiki_error::WikiError,
+};
+
+use nom::{bytes::complete::tag, IResult};
+
+// extension tags whose content is not wikitext
+const RAW: &[&str] = &[
+  "categorytree",
+  "ce",
+  "chem",
+  "gallery",
+  "graph",
+  "hiero",
+  "inputbox",
+  "mapframe",
+  "maplink",
+  "math",
+  "nowiki",
+  "pre",
+  "score",
+  "source",
+  "syntaxhighlight",
+  "templatedata",
+  "timeline",
+];
+// extension tags holding wikitext, the `|` of a template argument does not split them
+const EXTENSIONS: &[&str] =
+  &["includeonly", "indicator", "noinclude", "onlyinclude", "poem", "ref", "references", "section"];
+const HTML: &[&str] = &[
+  "abbr",
+  "b",
+  "bdi",
+  "bdo",
+  "big",
+  "blockquote",
+  "br",
+  "caption",
+  "center",
+  "cite",
+  "code",
+  "data",
+  "dd",
+  "del",
+  "dfn",
+  "div",
+  "dl",
+  "dt",
+  "em",
+  "font",
+  "h1",
+  "h2",
+  "h3",
+  "h4",
+  "h5",
+  "h6",
+  "hr",
+  "i",
+  "ins",
+  "kbd",
+  "li",
+  "mark",
+  "ol",
+  "p",
+  "q",
+  "rb",
+  "rp",
+  "rt",
+  "rtc",
+  "ruby",
+  "s",
+  "samp",
+  "small",
+  "span",
+  "strike",
+  "strong",
+  "sub",
+  "sup",
+  "table",
+  "td",
+  "th",
+  "time",
+  "tr",
+  "tt",
+  "u",
+  "ul",
+  "var",
+  "wbr",
+];
+// never have content, with or without the `/`
+const VOID: &[&str] = &["br", "hr", "wbr"];
+
+// the rest after `</name>`, when that is what `src` starts with
+fn closing<'a>(src: &'a str, name: &str) -> Option<&'a str> {
+  let tail = src.strip_prefix("</")?;
+  if !tail.get(..name.len()).is_some_and(|it| it.eq_ignore_ascii_case(name)) {
+    return None;
+  }
+  tail[name.len()..].trim_start().strip_prefix('>')
+}
+
+/// `<name attributes>content</name>` or `<name attributes/>`. Only tags MediaWiki knows are tags,
+/// `a <b> c` with anything else is text.
+pub(super) fn element<'a>(src: &'a str, stop: &dyn Fn(&str) -> bool) -> IResult<&'a str, Node, WikiError<&'a str>> {
+  let (src, _) = tag("<")(src)?;
+  let size = src.find(|c: char| !(c.is_ascii_alphanumeric() || c == '-')).unwrap_or(src.len());
+  let name = src[..size].to_ascii_lowercase();
+  let known = |list: &[&str]| list.contains(&name.as_str());
+  let src = &src[size..];
+  if !(known(RAW) || known(EXTENSIONS) || known(HTML))
+    || !src.starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>')
+  {
+    return Err(WikiError::Nom(src, nom::error::ErrorKind::Tag).into());
+  }
+  let end = src.find('>').ok_or(WikiError::OpenNotMatchesClose)?;
+  let (written, src) = (&src[..end], &src[end + 1..]);
+  let (written, empty) = match written.trim_end().strip_suffix('/') {
+    Some(written) => (written, true),
+    None => (written, known(VOID)),
+  };
+  let attributes = attributes(written);
+  let (src, body) = if empty {
+    (src, TagBody::Empty)
+  } else if known(RAW) {
+    // raw content needs its closing tag, `<nowiki>` alone is text
+    let (start, tail) = src
+      .match_indices("</")
+      .find_map(|(start, _)| closing(&src[start..], &name).map(|tail| (start, tail)))
+      .ok_or(WikiError::OpenNotMatchesClose)?;
+    (tail, TagBody::Raw(src[..start].to_owned()))
+  } else {
+    // HTML left open ends with whatever contains it
+    let html = !known(EXTENSIONS);
+    let (src, children) = blocks(src, &|s| closing(s, &name).is_some() || (html && stop(s)), Flow::Nested);
+    // an extension tag is only one once closed, a stray `<ref>` is text instead of taking the rest of the page
+    match closing(src, &name) {
+      Some(src) => (src, TagBody::Nodes(children)),
+      None if html => (src, TagBody::Nodes(children)),
+      None => return Err(WikiError::OpenNotMatchesClose.into()),
+    }
+  };
+  Ok((src, Node::Tag(Tag { name, attributes, body })))
+}
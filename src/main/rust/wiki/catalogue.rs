@@ -0,0 +1,325 @@
+#[allow(unused)]
+use crate::wiki as m;
+use m::{
+  ast::{plain_text, Heading, Node},
+  section::Section,
+};
+
+use lazy_static::lazy_static;
+use std::{
+  collections::{BTreeMap, HashMap, HashSet},
+  path::Path,
+};
+
+lazy_static! {
+  static ref CATALOGUE: Catalogue = Catalogue::configured();
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CatalogueError {
+  #[error("cannot read {0}: {1}")]
+  Unreadable(String, String),
+}
+
+// `sections.json` as it is written: the sections of each species, the species that are entries of
+// their own, what each heading names on the English Wiktionary and on the other editions
+#[derive(serde::Deserialize)]
+struct Tables {
+  species: BTreeMap<String, Vec<Section>>,
+  #[serde(default)]
+  entries: Vec<String>,
+  headings: HashMap<String, Section>,
+  #[serde(default)]
+  editions: HashMap<String, Edition>,
+}
+
+/// Which section a heading names and which species a section is of. Read from `sections.json` in
+/// the configuration directory, the list shipped in `config/widictor` when there is none there.
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(from = "Tables")]
+pub struct Catalogue {
+  // by language code, `en` among them
+  editions: HashMap<String, Edition>,
+  species: HashMap<Section, usize>,
+  entries: HashSet<usize>,
+}
+impl From<Tables> for Catalogue {
+  fn from(src: Tables) -> Self {
+    let entries = src.species.keys().enumerate().filter(|(_, it)| src.entries.contains(it)).map(|(id, _)| id).collect();
+    let species = src
+      .species
+      .into_iter()
+      .enumerate()
+      .flat_map(|(id, (_, sections))| sections.into_iter().map(move |it| (it, id)))
+      .collect();
+    let mut editions = src.editions;
+    let english =
+      Vocabulary { level: 2, language: None, templates: Vec::new(), qualified: false, headings: src.headings };
+    editions.insert("en".to_owned(), english.into());
+    Self { editions, species, entries }
+  }
+}
+
+// an edition as `sections.json` has it
+#[derive(serde::Deserialize)]
+struct Vocabulary {
+  level: u8,
+  #[serde(default)]
+  language: Option<String>,
+  #[serde(default)]
+  templates: Vec<String>,
+  #[serde(default)]
+  qualified: bool,
+  headings: HashMap<String, Section>,
+}
+
+/// How the headings of one Wiktionary edition are written. Languages are on `level`, named by the
+/// `language` template where there is one: `{{langue|fr}}`, or `{{-ru-}}` with `-*-`. Sections are
+/// named by the first argument of one of `templates`, `{{S|nom|fr}}`, by the name of any other
+/// template, `{{Übersetzungen}}`, or by the plain text of the heading. Where headings are `qualified`,
+/// what follows a comma is not part of the name: `Substantiv, n` is a noun.
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(from = "Vocabulary")]
+pub struct Edition {
+  level: u8,
+  language: Option<String>,
+  templates: Vec<String>,
+  qualified: bool,
+  // by lower-case heading
+  headings: HashMap<String, Section>,
+}
+impl From<Vocabulary> for Edition {
+  fn from(src: Vocabulary) -> Self {
+    let headings = src.headings.into_iter().map(|(heading, section)| (heading.to_lowercase(), section)).collect();
+    Self { level: src.level, language: src.language, templates: src.templates, qualified: src.qualified, headings }
+  }
+}
+impl Edition {
+  /// Headings are compared without regard to case and to a number at the end, `Etymology 2`; any
+  /// other heading is `Other`, as it is written.
+  pub fn section(&self, heading: &str) -> Section {
+    let written = heading.trim();
+    let heading = written.to_lowercase();
+    let heading = match heading.split_once(',') {
+      Some((name, _)) if self.qualified => name.trim_end(),
+      _ => &heading,
+    };
+    let bare = heading.trim_end_matches(|c: char| c.is_ascii_digit()).trim_end();
+    self.headings.get(bare).cloned().unwrap_or_else(|| Section::Other(written.to_owned()))
+  }
+  /// The level the heading would be on in the English edition, languages on the second.
+  pub fn level(&self, heading: &Heading) -> u8 {
+    (heading.level + 2).saturating_sub(self.level).clamp(1, 6)
+  }
+  /// The heading as the English edition would have it, `== {{langue|fr}} ==` is `==fr==` and
+  /// `=== {{S|nom|fr}} ===` is `===nom===`, for `Language` and `WordSection` to read.
+  pub fn line(&self, heading: &Heading) -> String {
+    let language =
+      if heading.level == self.level { heading.title.iter().find_map(|node| self.language_of(node)) } else { None };
+    let title = language.unwrap_or_else(|| heading.title.iter().map(|node| self.title_of(node)).collect());
+    let marks = "=".repeat(self.level(heading) as usize);
+    format!("{}{}{}", marks, title.trim(), marks)
+  }
+  // the language a template names
+  fn language_of(&self, node: &Node) -> Option<String> {
+    let (template, pattern) = match (node, &self.language) {
+      (Node::Template(template), Some(pattern)) => (template, pattern),
+      _ => return None,
+    };
+    let name = plain_text(&template.name);
+    let name = name.trim();
+    match pattern.split_once('*') {
+      Some((before, after)) => {
+        name.strip_prefix(before)?.strip_suffix(after).filter(|it| !it.is_empty()).map(str::to_owned)
+      }
+      None if name == pattern => first_argument(node),
+      None => None,
+    }
+  }
+  // what a piece of a title says of the section
+  fn title_of(&self, node: &Node) -> String {
+    match node {
+      Node::Template(template) => {
+        let name = plain_text(&template.name).trim().to_owned();
+        if self.templates.contains(&name) {
+          first_argument(node).unwrap_or_default()
+        } else {
+          name
+        }
+      }
+      node => plain_text(std::slice::from_ref(node)),
+    }
+  }
+}
+fn first_argument(node: &Node) -> Option<String> {
+  match node {
+    Node::Template(template) => {
+      template.args.iter().find(|it| it.name.is_none()).map(|it| plain_text(&it.value).trim().to_owned())
+    }
+    _ => None,
+  }
+}
+
+impl Catalogue {
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CatalogueError> {
+    let path = path.as_ref();
+    let unreadable = |e: &dyn std::fmt::Display| CatalogueError::Unreadable(path.display().to_string(), e.to_string());
+    let src = std::fs::read(path).map_err(|e| unreadable(&e))?;
+    serde_json::from_slice(&src).map_err(|e| unreadable(&e))
+  }
+  /// The Wiktionary:Entry layout list this was built with.
+  pub fn builtin() -> Self {
+    serde_json::from_str(include_str!("../../../../config/widictor/sections.json")).expect("sections.json is valid")
+  }
+  fn configured() -> Self {
+    let path = crate::dirs::config().join("sections.json");
+    if !path.exists() {
+      return Self::builtin();
+    }
+    Self::load(&path).unwrap_or_else(|e| {
+      log::warn!("{}, using the built-in sections", e);
+      Self::builtin()
+    })
+  }
+  pub fn global() -> &'static Self {
+    &CATALOGUE
+  }
+  pub fn english(&self) -> &Edition {
+    &self.editions["en"]
+  }
+  /// A title may name the edition it is on the way interwiki links do, `fr:chat`; the English
+  /// edition is the one otherwise. Gives the code of the edition, the edition and the title on it.
+  pub fn split<'a>(&self, title: &'a str) -> (&'a str, &Edition, &'a str) {
+    match title.split_once(':').and_then(|(code, rest)| Some((code, self.editions.get(code)?, rest))) {
+      Some(split) => split,
+      None => ("en", self.english(), title),
+    }
+  }
+  /// What the heading names on the English edition.
+  pub fn section(&self, heading: &str) -> Section {
+    self.english().section(heading)
+  }
+  fn species(&self, section: &Section) -> Option<usize> {
+    self.species.get(section).copied()
+  }
+  /// Whether the section is an entry of its own: its species is one of `entries`.
+  pub fn splits(&self, section: &Section) -> bool {
+    self.species(section).is_some_and(|it| self.entries.contains(&it))
+  }
+}
+
+#[cfg(test)]
+#[test]
+fn test_catalogue() {
+  let catalogue = Catalogue::builtin();
+  let other = |name: &str| Section::Other(name.to_owned());
+  [
+    ("Suffix", Section::Suffix),
+    ("Proper noun", Section::ProperNoun),
+    ("Etymology 2", Section::Etymology),
+    ("noun 1", Section::Noun),
+    ("Han character", Section::HanCharacter),
+    ("Kanji", Section::HanCharacter),
+    ("Coordinate terms", Section::CoordinateTerms),
+    ("Nouns and verbs", other("Nouns and verbs")),
+    ("Verbal noun", other("Verbal noun")),
+    ("Noun, n", other("Noun, n")),
+    ("Whatever", other("Whatever")),
+    ("", other("")),
+  ]
+  .iter()
+  .for_each(|(heading, section)| assert_eq!(&catalogue.section(heading), section, "{}", heading));
+  let word = catalogue.species(&Section::Noun);
+  assert!(word.is_some());
+  assert_eq!(catalogue.species(&Section::Idiom), word);
+  assert_eq!(catalogue.species(&Section::Letter), word);
+  assert!(catalogue.species(&Section::Quotations).is_some());
+  assert_ne!(catalogue.species(&Section::Quotations), word);
+  assert_eq!(catalogue.species(&Section::References), None);
+  assert!(catalogue.splits(&Section::Verb));
+  assert!(!catalogue.splits(&Section::Etymology));
+
+  let custom: Catalogue =
+    serde_json::from_str(r#"{ "species": { "word": ["Noun", "Verb"] }, "headings": { "Substantiv": "Noun" } }"#)
+      .unwrap();
+  assert_eq!(custom.section("Substantiv"), Section::Noun);
+  assert_eq!(custom.section("Noun"), other("Noun"));
+  assert_eq!(custom.species(&Section::Verb), custom.species(&Section::Noun));
+  assert!(!custom.splits(&Section::Noun));
+  // a section only the configuration knows
+  let custom: Catalogue = serde_json::from_str(
+    r#"{ "species": { "word": ["Noun", "Toponym"] }, "entries": ["word"], "headings": { "Toponym": "Toponym" } }"#,
+  )
+  .unwrap();
+  assert_eq!(custom.section("toponym"), other("Toponym"));
+  assert!(custom.splits(&other("Toponym")));
+  assert!(matches!(Catalogue::load("/nonexistent/sections.json"), Err(CatalogueError::Unreadable(..))));
+}
+
+#[cfg(test)]
+#[test]
+fn test_editions() {
+  use m::ast::parse;
+
+  let catalogue = Catalogue::builtin();
+  let other = |name: &str| Section::Other(name.to_owned());
+  let lines = |code: &str, page: &str| -> Vec<(String, Section)> {
+    let (_, edition, _) = catalogue.split(&format!("{}:x", code));
+    parse(page)
+      .iter()
+      .filter_map(|node| match node {
+        Node::Heading(heading) => {
+          let line = edition.line(heading);
+          let section = edition.section(line.trim_matches('='));
+          Some((line, section))
+        }
+        _ => None,
+      })
+      .collect()
+  };
+  let expected = |it: &[(&str, Section)]| -> Vec<(String, Section)> {
+    it.iter().map(|(line, section)| (line.to_string(), section.clone())).collect()
+  };
+  assert_eq!(
+    lines(
+      "fr",
+      "== {{langue|fr}} ==\n=== {{S|étymologie}} ===\n=== {{S|nom|fr|num=2}} ===\n==== {{S|traductions}} ===="
+    ),
+    expected(&[
+      ("==fr==", other("fr")),
+      ("===étymologie===", Section::Etymology),
+      ("===nom===", Section::Noun),
+      ("====traductions====", Section::Translations),
+    ])
+  );
+  assert_eq!(
+    lines(
+      "de",
+      "== Haus ({{Sprache|Deutsch}}) ==\n=== {{Wortart|Substantiv|Deutsch}}, {{n}} ===\n\
+       === {{Wortart|Substantiv|Deutsch}}, {{m}}, {{Wortart|Toponym|Deutsch}} ===\n==== {{Übersetzungen}} ===="
+    ),
+    expected(&[
+      ("==Deutsch==", other("Deutsch")),
+      ("===Substantiv, n===", Section::Noun),
+      ("===Substantiv, m, Toponym===", Section::Noun),
+      ("====Übersetzungen====", Section::Translations),
+    ])
+  );
+  assert_eq!(
+    lines(
+      "ru",
+      "= {{-ru-}} =\n=== Морфологические и синтаксические свойства ===\n==== Значение ====\n=== Этимология ==="
+    ),
+    expected(&[
+      ("==ru==", other("ru")),
+      ("====Морфологические и синтаксические свойства====", Section::Inflection),
+      ("=====Значение=====", Section::Definitions),
+      ("====Этимология====", Section::Etymology),
+    ])
+  );
+  assert_eq!(
+    lines("en", "==Latin==\n===Noun==="),
+    expected(&[("==Latin==", other("Latin")), ("===Noun===", Section::Noun)])
+  );
+  assert_eq!(catalogue.split("Appendix:Latin").2, "Appendix:Latin");
+}
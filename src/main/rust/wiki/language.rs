@@ -1,70 +1,67 @@
 #[allow(unused)]
 use crate::wiki as m;
 use m::{
+  ast::heading_line,
   wiki_error::WikiError,
-  word_section::{Tree, WordSection},
+  word_section::{Outline, WordSection},
 };
 
-use nom::{
-  bytes::complete::{tag, take_while1},
-  sequence::delimited,
-  IResult,
-};
-use std::rc::Rc;
+use nom::{error::ErrorKind, IResult};
 
+/// The sections of a language in the order of the page, what comes before the first heading first.
 #[derive(Debug)]
-pub struct Language<T, S: std::convert::Into<Rc<WordSection<T>>>> {
+pub struct Language<T> {
   pub name: String,
-  pub sections: Vec<S>,
-  phantom: std::marker::PhantomData<T>,
+  pub sections: Vec<WordSection<T>>,
 }
 
-impl Language<(), WordSection<()>> {
+impl Language<()> {
+  // a second level heading, whatever its name is written with
   fn language(src: &str) -> IResult<&str, &str, WikiError<&str>> {
-    Ok(delimited(tag("=="), take_while1(|c: char| c.is_alphabetic() || c.is_whitespace()), tag("=="))(src)?)
+    match heading_line(src)? {
+      (tail, (2, name)) if !name.is_empty() => Ok((tail, name)),
+      _ => Err(WikiError::Nom(src, ErrorKind::Tag).into()),
+    }
   }
 
   pub fn parse(input: &str) -> IResult<&str, Self, WikiError<&str>> {
     let value = Self::language(input)?;
 
-    Ok((value.0, Self { name: value.1.to_string(), sections: vec![WordSection::empty()], phantom: Default::default() }))
+    Ok((value.0, Self { name: value.1.to_string(), sections: vec![WordSection::empty()] }))
   }
 }
 
-impl<T> Language<T, WordSection<T>> {
+impl<T> Language<T> {
   pub fn section(&mut self) -> &mut WordSection<T> {
     self.sections.last_mut().unwrap()
   }
-  pub fn convert<N, TtoN: FnMut(T) -> N>(self, mut conv: TtoN) -> Language<N, WordSection<N>> {
-    Language {
-      name: self.name,
-      sections: self.sections.into_iter().map(|v| v.convert(&mut conv)).collect(),
-      phantom: Default::default(),
-    }
+  pub fn convert<N, TtoN: FnMut(T) -> N>(self, mut conv: TtoN) -> Language<N> {
+    Language { name: self.name, sections: self.sections.into_iter().map(|v| v.convert(&mut conv)).collect() }
   }
-  pub fn try_convert<N, E, TtoN: FnMut(T) -> Result<N, E>>(
-    self,
-    mut conv: TtoN,
-  ) -> Result<Language<N, WordSection<N>>, E> {
-    Ok(Language {
-      name: self.name,
-      sections: self.sections.into_iter().map(|v| v.try_convert(&mut conv)).collect::<Result<_, E>>()?,
-      phantom: Default::default(),
-    })
+  pub fn fold_convert<N, TtoN: FnMut(Vec<N>, T) -> Vec<N>>(self, mut conv: TtoN) -> Language<N> {
+    Language { name: self.name, sections: self.sections.into_iter().map(|v| v.fold_convert(&mut conv)).collect() }
   }
-  pub fn fold_convert<N, TtoN: FnMut(Vec<N>, T) -> Vec<N>>(self, mut conv: TtoN) -> Language<N, WordSection<N>> {
-    Language {
-      name: self.name,
-      sections: self.sections.into_iter().map(|v| v.fold_convert(&mut conv)).collect(),
-      phantom: Default::default(),
-    }
-  }
-  pub fn subdivide(self) -> Vec<Language<T, Rc<WordSection<T>>>> {
-    let name = self.name;
-    let tree = self.sections.into_iter().filter(|s| s.name.species().is_some()).collect::<Tree<_>>();
-    tree
-      .into_iter()
-      .map(|v| Language { name: name.clone(), sections: v.into_iter().collect(), phantom: Default::default() })
-      .collect()
+  /// The sections as the headings nest them.
+  pub fn outline(self) -> Outline<T> {
+    let mut sections = self.sections.into_iter();
+    let root = sections.next().unwrap_or_else(WordSection::empty);
+    Outline::new(root, sections)
   }
 }
+
+#[cfg(test)]
+#[test]
+fn test_language() {
+  [
+    ("==Latin==", Some("Latin")),
+    ("==Proto-Indo-European==", Some("Proto-Indo-European")),
+    ("== Ancient Greek == ", Some("Ancient Greek")),
+    ("==Mi'kmaq==", Some("Mi'kmaq")),
+    ("==Gĩkũyũ==", Some("Gĩkũyũ")),
+    ("===Noun===", None),
+    ("== ==", None),
+    ("Latin", None),
+  ]
+  .iter()
+  .for_each(|(q, a)| assert_eq!(Language::parse(q).ok().map(|it| it.1.name), a.map(str::to_owned), "{}", q));
+}
@@ -0,0 +1,101 @@
+#[allow(unused)]
+use crate::wiki as m;
+use m::{Telua, TeluaResult};
+
+use std::{
+  cell::RefCell,
+  ops::{Deref, DerefMut},
+};
+
+/// Initialized interpreters waiting to be reused. Spawning `lua5.1` and running the `mw` setup costs far
+/// more than a page conversion, so an instance is reset and kept instead of being thrown away.
+pub struct TeluaPool {
+  idle: RefCell<Vec<Telua>>,
+  capacity: usize,
+  spawn: Box<dyn Fn() -> TeluaResult<Telua>>,
+}
+impl TeluaPool {
+  /// At most `capacity` instances are kept idle; more may be checked out at once.
+  pub fn new(capacity: usize) -> Self {
+    Self::with_spawn(capacity, Telua::new)
+  }
+  /// Same as `new`, with instances made by `spawn` instead of `Telua::new`.
+  pub fn with_spawn<F: Fn() -> TeluaResult<Telua> + 'static>(capacity: usize, spawn: F) -> Self {
+    Self { idle: Default::default(), capacity, spawn: Box::new(spawn) }
+  }
+  pub fn idle(&self) -> usize {
+    self.idle.borrow().len()
+  }
+  /// Takes an idle instance or spawns a new one when there is none.
+  pub fn get(&self) -> TeluaResult<PooledTelua<'_>> {
+    let telua = match self.idle.borrow_mut().pop() {
+      Some(telua) => telua,
+      None => (self.spawn)()?,
+    };
+    Ok(PooledTelua { pool: self, telua: Some(telua) })
+  }
+  // an instance that is not kept is dropped, which stops its interpreter
+  fn put(&self, mut telua: Telua) {
+    if self.idle() >= self.capacity {
+      return;
+    }
+    match telua.reset() {
+      Ok(()) => self.idle.borrow_mut().push(telua),
+      Err(e) => log::warn!("dropping an interpreter that failed to reset: {}", e),
+    }
+  }
+}
+
+/// A checked out instance, it goes back to the pool when dropped.
+pub struct PooledTelua<'a> {
+  pool: &'a TeluaPool,
+  telua: Option<Telua>,
+}
+impl Deref for PooledTelua<'_> {
+  type Target = Telua;
+
+  fn deref(&self) -> &Telua {
+    self.telua.as_ref().unwrap()
+  }
+}
+impl DerefMut for PooledTelua<'_> {
+  fn deref_mut(&mut self) -> &mut Telua {
+    self.telua.as_mut().unwrap()
+  }
+}
+impl Drop for PooledTelua<'_> {
+  fn drop(&mut self) {
+    if let Some(telua) = self.telua.take() {
+      self.pool.put(telua);
+    }
+  }
+}
+
+#[test]
+fn test_pool() {
+  use std::{collections::HashMap, rc::Rc, sync::Arc, sync::Mutex};
+
+  let spawned = Rc::new(RefCell::new(0));
+  let pool = {
+    let spawned = spawned.clone();
+    TeluaPool::with_spawn(1, move || {
+      *spawned.borrow_mut() += 1;
+      let server = m::fake_mw(Arc::new(Mutex::new(HashMap::new())));
+      Telua::with_engine(Box::new(server.spawn(vec!["pkg".to_owned()])))
+    })
+  };
+  // two at once, only one is kept once they are back
+  let (first, second) = (pool.get().unwrap(), pool.get().unwrap());
+  assert_eq!((pool.idle(), *spawned.borrow()), (0, 2));
+  drop(first);
+  drop(second);
+  assert_eq!(pool.idle(), 1);
+  // the kept one is reused; once it can't be reset it is not kept anymore
+  let mut telua = pool.get().unwrap();
+  assert_eq!((pool.idle(), *spawned.borrow()), (0, 2));
+  telua.libs.remove("mw");
+  drop(telua);
+  assert_eq!(pool.idle(), 0);
+  drop(pool.get().unwrap());
+  assert_eq!((pool.idle(), *spawned.borrow()), (1, 3));
+}
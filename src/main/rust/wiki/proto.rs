@@ -0,0 +1,160 @@
+#[allow(unused)]
+use crate::wiki as m;
+
+use std::{collections::HashMap, fmt::Display, io, path::Path};
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeId {
+  Bool,
+  Float,
+  Integer,
+  String,
+}
+impl TypeId {
+  fn accepts(self, value: &str) -> bool {
+    let value = value.trim();
+    match self {
+      // what Module:yesno understands, the empty string being "not set"
+      Self::Bool => {
+        ["", "0", "1", "true", "false", "yes", "no", "y", "n", "on", "off"].contains(&value.to_lowercase().as_str())
+      }
+      Self::Float => value.parse::<f64>().is_ok(),
+      Self::Integer => value.parse::<i64>().is_ok(),
+      Self::String => true,
+    }
+  }
+}
+impl Display for TypeId {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      Self::Bool => "boolean",
+      Self::Float => "number",
+      Self::Integer => "integer",
+      Self::String => "string",
+    })
+  }
+}
+
+/// A parameter is either just its type, `"n": "Integer"`, or a table with the details.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Param {
+  Type(TypeId),
+  Table {
+    #[serde(rename = "type", default = "Param::string")]
+    type_id: TypeId,
+    #[serde(default)]
+    default: Option<String>,
+    #[serde(default)]
+    required: bool,
+    #[serde(default)]
+    doc: Option<String>,
+  },
+}
+impl Param {
+  fn string() -> TypeId {
+    TypeId::String
+  }
+  pub fn type_id(&self) -> TypeId {
+    match self {
+      Self::Type(type_id) | Self::Table { type_id, .. } => *type_id,
+    }
+  }
+  pub fn default(&self) -> Option<&str> {
+    match self {
+      Self::Table { default, .. } => default.as_deref(),
+      Self::Type(_) => None,
+    }
+  }
+  pub fn required(&self) -> bool {
+    matches!(self, Self::Table { required: true, .. })
+  }
+  pub fn doc(&self) -> Option<&str> {
+    match self {
+      Self::Table { doc, .. } => doc.as_deref(),
+      Self::Type(_) => None,
+    }
+  }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ProtoError {
+  #[error("cannot read {0}: {1}")]
+  Unreadable(String, String),
+  #[error("missing required argument `{name}`{}", .doc.as_ref().map(|it| format!(" ({})", it)).unwrap_or_default())]
+  Missing { name: String, doc: Option<String> },
+  #[error("argument `{name}` is `{value}`, which is not a valid {expected}")]
+  Invalid { name: String, value: String, expected: TypeId },
+}
+
+/// Optional description of the arguments a module takes, read from `<module>.proto` next to it.
+/// Arguments reach Lua as strings whatever it says, as with MediaWiki; it only checks them and fills
+/// in defaults.
+#[derive(serde::Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Proto(HashMap<String, Param>);
+impl Proto {
+  /// A module without a proto file has `None`; a file that is there must be valid.
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Self>, ProtoError> {
+    let path = path.as_ref();
+    let unreadable = |e: &dyn Display| ProtoError::Unreadable(path.display().to_string(), e.to_string());
+    match std::fs::read(path) {
+      Ok(src) => serde_json::from_slice(&src).map(Some).map_err(|e| unreadable(&e)),
+      Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+      Err(e) => Err(unreadable(&e)),
+    }
+  }
+  /// Fills in the defaults and checks every argument the proto knows of, the others are left as they are.
+  pub fn apply(&self, args: &mut HashMap<String, String>) -> Result<(), ProtoError> {
+    let mut names: Vec<&String> = self.0.keys().collect();
+    // the first problem in name order, so the same call always reports the same one
+    names.sort();
+    for name in names {
+      let param = &self.0[name];
+      match (args.get(name), param.default()) {
+        (Some(value), _) if !param.type_id().accepts(value) => {
+          return Err(ProtoError::Invalid { name: name.clone(), value: value.clone(), expected: param.type_id() });
+        }
+        (Some(_), _) => {}
+        (None, Some(default)) => {
+          args.insert(name.clone(), default.to_owned());
+        }
+        (None, None) if param.required() => {
+          return Err(ProtoError::Missing { name: name.clone(), doc: param.doc().map(str::to_owned) });
+        }
+        (None, None) => {}
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+#[test]
+fn test_proto() {
+  let proto: Proto = serde_json::from_str(
+    r#"{
+      "1": "String",
+      "n": { "type": "Integer", "default": "1", "doc": "how many" },
+      "ratio": "Float",
+      "bold": { "type": "Bool" },
+      "lang": { "required": true, "doc": "language code" }
+    }"#,
+  )
+  .unwrap();
+  assert_eq!(proto.0.get("n").and_then(Param::doc), Some("how many"));
+  assert_eq!(proto.0.get("lang").map(Param::type_id), Some(TypeId::String));
+
+  let args = |list: &[(&str, &str)]| -> HashMap<String, String> {
+    list.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+  };
+  let mut ok = args(&[("1", " word "), ("lang", "la"), ("bold", "yes"), ("extra", "x")]);
+  proto.apply(&mut ok).unwrap();
+  assert_eq!(ok, args(&[("1", " word "), ("lang", "la"), ("bold", "yes"), ("extra", "x"), ("n", "1")]));
+
+  let error = proto.apply(&mut args(&[("lang", "la"), ("n", "two")])).unwrap_err();
+  assert_eq!(error.to_string(), "argument `n` is `two`, which is not a valid integer");
+  let error = proto.apply(&mut args(&[("ratio", "0.5")])).unwrap_err();
+  assert_eq!(error.to_string(), "missing required argument `lang` (language code)");
+
+  assert!(matches!(Proto::load("/nonexistent/module.proto"), Ok(None)));
+}
@@ -1,43 +1,101 @@
 #[allow(unused)]
 use crate::wiki as m;
+use m::catalogue::Catalogue;
 
-#[derive(Debug, PartialEq, std::cmp::Eq, std::hash::Hash, Clone, Copy)]
+/// A heading of an entry, as `Catalogue` names it; Wiktionary:Entry layout lists them all. Any other
+/// name is `Other`: a heading the catalogue does not know, as it is written, or a section `sections.json`
+/// names that is not listed here.
+#[derive(Debug, PartialEq, std::cmp::Eq, std::hash::Hash, Clone, serde::Deserialize)]
+#[serde(remote = "Self")]
 pub enum Section {
   Null,
-  Unknown,
+  #[serde(skip)]
+  Other(String),
 
+  Description,
+  GlyphOrigin,
+  Etymology,
+  Pronunciation,
+  Production,
+  /// Senses under a heading of their own, ru `Значение`; English has them right under the part of speech.
+  Definitions,
+  UsageNotes,
+  ReconstructionNotes,
+  Inflection,
   Declension,
+  Conjugation,
+  Mutation,
+  Quotations,
+  AlternativeForms,
+  AlternativeScripts,
+  AlternativeReconstructions,
+  Synonyms,
+  Antonyms,
+  Hypernyms,
+  Hyponyms,
+  Meronyms,
+  Holonyms,
+  Comeronyms,
+  Troponyms,
+  Parasynonyms,
+  CoordinateTerms,
   DerivedTerms,
   RelatedTerms,
+  Collocations,
+  Compounds,
   Descendants,
+  Translations,
+  Trivia,
   SeeAlso,
-  Etymology,
-  Pronunciation,
   References,
   FurtherReading,
-  AlternativeForms,
-  Conjugation,
-  UsageNotes,
-  Translations,
   Anagrams,
-  Synonyms,
-  Antonyms,
-  Determiner,
-  Contraction,
-  Inflection,
-  Compounds,
+  Statistics,
 
+  Adjective,
+  Adverb,
+  Ambiposition,
+  Article,
+  Circumposition,
+  Classifier,
   Conjunction,
+  Contraction,
+  Counter,
+  Determiner,
+  Ideophone,
+  Interjection,
   Noun,
-  Verb,
-  Adjective,
+  Numeral,
   Participle,
+  Particle,
+  Postposition,
   Preposition,
   Pronoun,
-  Interjection,
-  Adverb,
-  Numeral,
-  Particle,
+  ProperNoun,
+  Verb,
+  Circumfix,
+  CombiningForm,
+  Infix,
+  Interfix,
+  Prefix,
+  Root,
+  Suffix,
+  DiacriticalMark,
+  Letter,
+  Ligature,
+  Number,
+  PunctuationMark,
+  Syllable,
+  Symbol,
+  Phrase,
+  PrepositionalPhrase,
+  Proverb,
+  Idiom,
+  HanCharacter,
+  Romanization,
+  Abbreviation,
+  Acronym,
+  Initialism,
 }
 
 impl Default for Section {
@@ -47,121 +105,26 @@ impl Default for Section {
 }
 
 impl Section {
-  pub(crate) fn species(&self) -> Option<usize> {
-    Some(match self {
-      Self::Compounds
-      | Self::Conjunction
-      | Self::Noun
-      | Self::Verb
-      | Self::Adjective
-      | Self::Participle
-      | Self::Preposition
-      | Self::Pronoun
-      | Self::Interjection
-      | Self::Adverb
-      | Self::Numeral
-      | Self::Particle => 0,
-      Self::Declension | Self::Conjugation | Self::Inflection => 1,
-      Self::DerivedTerms => 2,
-      Self::RelatedTerms => 3,
-      Self::Descendants => 4,
-      Self::Etymology => 5,
-      Self::Pronunciation => 6,
-      Self::UsageNotes => 7,
-      Self::Synonyms => 8,
-      Self::Antonyms => 9,
-
-      Self::Null
-      | Self::Unknown
-      | Self::SeeAlso
-      | Self::Anagrams
-      | Self::Translations
-      | Self::References
-      | Self::FurtherReading
-      | Self::AlternativeForms
-      | Self::Determiner
-      | Self::Contraction => return None,
-    })
-  }
-
-  /*
-  fn general_species(&self) -> Option<SectionSpecies> {
-    Some(match self {
-      Self::Conjunction | Self::Noun | Self::Verb | Self::Adjective | Self::Participle | Self::Preposition | Self::Pronoun | Self::Interjection | Self::Adverb | Self::Numeral | Self::Particle => SectionSpecies::Word,
-      Self::Declension | Self::Conjugation | Self::Inflection => SectionSpecies::Mutation,
-      Self::Compounds | Self::DerivedTerms | Self::RelatedTerms | Self::Descendants | Self::Synonyms | Self::Antonyms => SectionSpecies::Provided,
-      Self::Etymology => SectionSpecies::Etymology,
-      Self::Pronunciation => SectionSpecies::Pronunciation,
-      Self::UsageNotes => SectionSpecies::UsageNotes,
-
-      Self::Unknown | Self::SeeAlso | Self::Anagrams | Self::Translations | Self::References | Self::FurtherReading | Self::AlternativeForms | Self::Determiner | Self::Contraction => return None,
-    })
+  /// Sections that are entries of their own, a noun and a verb say, as `Catalogue` has it.
+  pub(crate) fn splits(&self) -> bool {
+    Catalogue::global().splits(self)
   }
+}
 
-  fn tag(&self) -> Option<&'static str> {
-    match self {
-      Self::Unknown | Self::Compounds | Self::Declension | Self::DerivedTerms | Self::RelatedTerms | Self::Descendants | Self::SeeAlso | Self::Etymology | Self::Pronunciation | Self::References | Self::FurtherReading | Self::AlternativeForms | Self::Conjugation | Self::UsageNotes | Self::Translations | Self::Anagrams | Self::Synonyms | Self::Antonyms | Self::Determiner | Self::Contraction | Self::Inflection => None,
+// names in `sections.json` that are not variants are `Other`, so a section can be added there alone
+impl<'de> serde::Deserialize<'de> for Section {
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    use serde::de::{value, IntoDeserializer};
 
-      Self::Conjunction => Some("conjunction"),
-      Self::Noun => Some("noun"),
-      Self::Verb => Some("verb"),
-      Self::Adjective => Some("adjective"),
-      Self::Participle => Some("participle"),
-      Self::Preposition => Some("preposition"),
-      Self::Pronoun => Some("pronoun"),
-      Self::Interjection => Some("interjection"),
-      Self::Adverb => Some("adverb"),
-      Self::Numeral => Some("numeral"),
-      Self::Particle => Some("particle"),
-    }
+    let name = String::deserialize(deserializer)?;
+    let known = Self::deserialize(IntoDeserializer::<value::Error>::into_deserializer(name.as_str()));
+    Ok(known.unwrap_or(Self::Other(name)))
   }
-  */
 }
 
 impl From<&str> for Section {
   fn from(value: &str) -> Self {
-    let mut list = [
-      ("Declension", Section::Declension),
-      ("Derived terms", Section::DerivedTerms),
-      ("Related terms", Section::RelatedTerms),
-      ("Descendants", Section::Descendants),
-      ("See also", Section::SeeAlso),
-      ("Etymology", Section::Etymology),
-      ("Pronunciation", Section::Pronunciation),
-      ("References", Section::References),
-      ("Further reading", Section::FurtherReading),
-      ("Alternative forms", Section::AlternativeForms),
-      ("Conjugation", Section::Conjugation),
-      ("Usage notes", Section::UsageNotes),
-      ("Translations", Section::Translations),
-      ("Anagrams", Section::Anagrams),
-      ("Conjunction", Section::Conjunction),
-      ("Synonyms", Section::Synonyms),
-      ("Antonyms", Section::Antonyms),
-      ("Determiner", Section::Determiner),
-      ("Contraction", Section::Contraction),
-      ("Inflection", Section::Inflection),
-      ("Compounds", Section::Compounds),
-      ("Noun", Section::Noun),
-      ("Proper noun", Section::Noun),
-      ("Verb", Section::Verb),
-      ("Adjective", Section::Adjective),
-      ("Preposition", Section::Preposition),
-      ("Pronoun", Section::Pronoun),
-      ("Participle", Section::Participle),
-      ("Interjection", Section::Interjection),
-      ("Adverb", Section::Adverb),
-      ("Numeral", Section::Numeral),
-      ("Particle", Section::Particle),
-    ]
-    .iter()
-    .map(|(template, value)| move |test: &str| if test.starts_with(template) { Some(value.clone()) } else { None });
-    while let Some(l) = list.next() {
-      if let Some(value) = l(value) {
-        return value;
-      }
-    }
-    Self::Unknown
+    Catalogue::global().section(value)
   }
 }
 
@@ -169,5 +132,7 @@ impl From<&str> for Section {
 #[test]
 fn test() {
   assert_eq!(Section::Noun, Section::from("Noun"));
-  assert_eq!(Section::Unknown, Section::from("U"));
+  assert_eq!(Section::Other("U".to_owned()), Section::from("U"));
+  let names: Vec<Section> = serde_json::from_str(r#"["Noun", "Toponym"]"#).unwrap();
+  assert_eq!(names, vec![Section::Noun, Section::Other("Toponym".to_owned())]);
 }
@@ -0,0 +1,102 @@
+#[allow(unused)]
+use crate::wiki as m;
+use m::ast::{Node, Tag, TagBody};
+
+use std::collections::HashMap;
+
+/// Makes the text of a tag; `render` turns nodes into text the way the rest of the page is.
+pub type TagHandler = Box<dyn Fn(&Tag, &mut dyn FnMut(&[Node]) -> String) -> String>;
+
+/// What each tag turns into in the text of an entry. Tags no handler is registered for give their
+/// content, extensions a wiki adds register theirs with `register`.
+pub struct TagHandlers(HashMap<String, TagHandler>);
+impl TagHandlers {
+  pub fn empty() -> Self {
+    Self(HashMap::new())
+  }
+  /// The tags Wiktionary pages are written with.
+  pub fn builtin() -> Self {
+    let mut handlers = Self::empty();
+    // footnotes stay next to what they note, `<references/>` has nothing left to list
+    handlers.register("ref", |tag, render| match &tag.body {
+      TagBody::Nodes(nodes) => format!("[{}]", render(nodes).trim()),
+      _ => tag.attributes.get("name").map(|it| format!("[{}]", it)).unwrap_or_default(),
+    });
+    handlers.register("references", |_, _| String::new());
+    handlers.register("math", |tag, _| format!("${}$", raw(tag).trim()));
+    for name in &["sup", "sub"] {
+      handlers.register(name, |tag, render| format!("<{0}>{1}</{0}>", tag.name, content(tag, render)));
+    }
+    handlers.register("br", |_, _| "\n".to_owned());
+    // images with their captions, there is no text in them
+    handlers.register("gallery", |_, _| String::new());
+    handlers
+  }
+  pub fn register<F: Fn(&Tag, &mut dyn FnMut(&[Node]) -> String) -> String + 'static>(
+    &mut self,
+    name: &str,
+    handler: F,
+  ) {
+    self.0.insert(name.to_lowercase(), Box::new(handler));
+  }
+  pub fn render(&self, tag: &Tag, render: &mut dyn FnMut(&[Node]) -> String) -> String {
+    match self.0.get(&tag.name) {
+      Some(handler) => handler(tag, render),
+      None => content(tag, render),
+    }
+  }
+}
+
+/// The content of a tag as text: `<nowiki>` and `<pre>` as written, the rest rendered.
+pub fn content(tag: &Tag, render: &mut dyn FnMut(&[Node]) -> String) -> String {
+  match &tag.body {
+    TagBody::Nodes(nodes) => render(nodes),
+    TagBody::Raw(raw) => raw.clone(),
+    TagBody::Empty => String::new(),
+  }
+}
+fn raw(tag: &Tag) -> &str {
+  match &tag.body {
+    TagBody::Raw(raw) => raw,
+    _ => "",
+  }
+}
+
+#[cfg(test)]
+#[test]
+fn test_tags() {
+  use m::ast::{parse, plain_text};
+
+  let handlers = TagHandlers::builtin();
+  let render = |src: &str| -> String {
+    let mut out = String::new();
+    let nodes = match parse(src).pop() {
+      Some(Node::Paragraph(nodes)) => nodes,
+      node => panic!("{:?}", node),
+    };
+    for node in &nodes {
+      match node {
+        Node::Tag(tag) => out += &handlers.render(tag, &mut |nodes| plain_text(nodes)),
+        node => out += &plain_text(std::slice::from_ref(node)),
+      }
+    }
+    out
+  };
+  assert_eq!(
+    render("x<ref name=\"a\">[[b|c]]</ref><ref name=a /> m<sup>2</sup> <math>a^2</math><br>y<references/>"),
+    "x[c][a] m<sup>2</sup> $a^2$\ny"
+  );
+  assert_eq!(render("<nowiki>[[x]]</nowiki> <span class=\"g\">m</span><pre>''p''</pre>"), "[[x]] m''p''");
+  assert_eq!(render("<gallery>\nFile:a.jpg|a\n</gallery>z"), "z");
+
+  let mut handlers = TagHandlers::empty();
+  handlers.register("poem", |tag, render| content(tag, render).replace('\n', " / "));
+  let poem = match &parse("<poem>a\nb</poem>")[0] {
+    Node::Paragraph(nodes) => nodes[0].clone(),
+    node => panic!("{:?}", node),
+  };
+  match poem {
+    Node::Tag(tag) => assert_eq!(handlers.render(&tag, &mut |nodes| plain_text(nodes)), "a / b"),
+    node => panic!("{:?}", node),
+  }
+}
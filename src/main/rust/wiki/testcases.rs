@@ -0,0 +1,125 @@
+#[allow(unused)]
+use crate::wiki as m;
+use m::{Telua, TeluaResult};
+
+use crate::{lua_table, scribunto::*};
+use std::fmt::Display;
+
+/// One `test*` function of a suite; `failure` holds the assertion or error message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCase {
+  pub name: String,
+  pub failure: Option<String>,
+}
+
+/// What running `Module:<module>/testcases` gave, tests in name order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestReport {
+  pub module: String,
+  pub cases: Vec<TestCase>,
+}
+impl TestReport {
+  pub fn passed(&self) -> usize {
+    self.cases.iter().filter(|it| it.failure.is_none()).count()
+  }
+  pub fn failures(&self) -> impl Iterator<Item = &TestCase> {
+    self.cases.iter().filter(|it| it.failure.is_some())
+  }
+  pub fn is_success(&self) -> bool {
+    self.failures().next().is_none()
+  }
+}
+impl Display for TestReport {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}: {} passed, {} failed", self.module, self.passed(), self.cases.len() - self.passed())?;
+    for case in self.failures() {
+      write!(f, "\n  {}: {}", case.name, case.failure.as_deref().unwrap_or_default())?;
+    }
+    Ok(())
+  }
+}
+
+impl Telua {
+  /// Runs the ScribuntoUnit suite of a module, `links` runs `Module:links/testcases`. The suite
+  /// gets the same `mw` environment as `call` gives a module; a failed test is part of the report,
+  /// only a suite that cannot run at all is an error.
+  pub fn run_tests(&mut self, module: &str) -> TeluaResult<TestReport> {
+    let module = module.strip_prefix("Module:").unwrap_or(module);
+    let file = format!("{}/testcases", module);
+    let frame = self.new_frame(Default::default(), None)?;
+    let cases = self.session(&file, |telua| {
+      let suite = telua.machine.load_file(&file, &format!("{}.lua", file))?;
+      let runner = telua
+        .machine
+        .call_file("testcases", "testcases.lua")?
+        .get_function(1)
+        .ok_or_else(|| ScribuntoError::protocol("testcases.lua returned no function"))?;
+      let results = telua.machine.call(runner, lua_table![suite, frame.into_raw()])?;
+      Ok(results.get_as::<_, Vec<LuaTable>>(1)?.into_iter().map(TestCase::from_lua).collect::<Result<Vec<_>, _>>()?)
+    })?;
+    Ok(TestReport { module: file, cases })
+  }
+}
+impl TestCase {
+  fn from_lua(result: LuaTable) -> Result<Self, LuaTypeError> {
+    let failure = if result.get_as("passed")? {
+      None
+    } else {
+      Some(result.get_as::<_, Option<String>>("message")?.unwrap_or_default())
+    };
+    Ok(Self { name: result.get_as("name")?, failure })
+  }
+}
+
+#[cfg(test)]
+#[test]
+fn test_run_tests() {
+  use std::sync::{Arc, Mutex};
+
+  let dir = std::env::temp_dir().join(format!("widictor-testcases-{}", std::process::id()));
+  std::fs::create_dir_all(dir.join("links")).unwrap();
+  std::fs::write(dir.join("links/testcases.lua"), "return {}").unwrap();
+  let server = m::fake_mw(Arc::new(Mutex::new(Default::default()))).module("testcases", |lua, _| {
+    let run = lua.function(|_, args| {
+      assert!(args.get_function(1).is_some());
+      assert!(args.get_table(2).and_then(|it| it.get_table("args")).is_some());
+      let passed = lua_table! { "name" => "test_link", "passed" => true };
+      let failed = lua_table! { "name" => "test_script", "passed" => false, "message" => "Failed to assert" };
+      Ok(lua_table![lua_table![passed, failed]])
+    });
+    Ok(lua_table![run])
+  });
+  let includes = vec!["pkg".to_owned(), dir.to_string_lossy().into_owned()];
+  let mut telua = Telua::with_engine(Box::new(server.spawn(includes))).unwrap();
+  let live = telua.machine.live_chunks();
+  let report = telua.run_tests("Module:links").unwrap();
+  assert_eq!(telua.machine.live_chunks(), live);
+  assert_eq!(report.passed(), 1);
+  assert!(!report.is_success());
+  assert_eq!(report.to_string(), "links/testcases: 1 passed, 1 failed\n  test_script: Failed to assert");
+  assert!(matches!(telua.run_tests("nowhere"), Err(ScribuntoError::ModuleNotFound(_))));
+  let _ = std::fs::remove_dir_all(dir);
+}
+
+#[cfg(test)]
+#[test]
+#[ignore = "needs lua5.1, run with --ignored"]
+fn test_testcases_lua() {
+  let dir = std::env::temp_dir().join(format!("widictor-testcases-lua-{}", std::process::id()));
+  std::fs::create_dir_all(dir.join("links")).unwrap();
+  std::fs::write(
+    dir.join("links/testcases.lua"),
+    "local suite = { testValue = 'not a function' }\n\
+     function suite:test_sum() if 1 + 1 ~= 2 then error('no sum') end end\n\
+     function suite:test_link() error({ ScribuntoUnit = true, text = 'no link', message = 'links' }) end\n\
+     function suite:helper() error('not a test') end\n\
+     return suite\n",
+  )
+  .unwrap();
+  let mut telua = Telua::build(LuaInstance::builder().path(&dir)).unwrap();
+  let report = telua.run_tests("links").unwrap();
+  let case =
+    |name: &str, failure: Option<&str>| TestCase { name: name.to_owned(), failure: failure.map(str::to_owned) };
+  assert_eq!(report.cases, vec![case("test_link", Some("links: no link")), case("test_sum", None)]);
+  let _ = std::fs::remove_dir_all(dir);
+}
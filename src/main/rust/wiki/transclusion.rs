@@ -0,0 +1,146 @@
+#[allow(unused)]
+use crate::wiki as m;
+
+/// How a page is read: on its own, or as a template in another page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+  Direct,
+  Transcluded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mark {
+  IncludeOnly,
+  NoInclude,
+  OnlyInclude,
+}
+
+// `<noinclude>`, `</includeonly >` or `<onlyinclude/>` at the start of `src`: which, whether it
+// closes, whether it is empty, and its size
+fn include_tag(src: &str) -> Option<(Mark, bool, bool, usize)> {
+  let tail = src.strip_prefix('<')?;
+  let (close, tail) = match tail.strip_prefix('/') {
+    Some(tail) => (true, tail),
+    None => (false, tail),
+  };
+  let name = |it: &str| tail.get(..it.len()).filter(|head| head.eq_ignore_ascii_case(it)).map(|_| &tail[it.len()..]);
+  let (include, tail) =
+    [("includeonly", Mark::IncludeOnly), ("noinclude", Mark::NoInclude), ("onlyinclude", Mark::OnlyInclude)]
+      .iter()
+      .find_map(|(it, include)| name(it).map(|tail| (*include, tail)))?;
+  let rest = tail.trim_start();
+  let (empty, rest) = match rest.strip_prefix('/') {
+    Some(rest) => (true, rest),
+    None => (false, rest),
+  };
+  rest.strip_prefix('>').map(|end| (include, close, empty, src.len() - end.len()))
+}
+
+// a comment or a `<nowiki>`/`<pre>` at the start of `src`, whose content is not looked into
+fn opaque(src: &str) -> Option<usize> {
+  if let Some(tail) = src.strip_prefix("<!--") {
+    return Some(tail.find("-->").map_or(src.len(), |it| 4 + it + 3));
+  }
+  let tail = src.strip_prefix('<')?;
+  ["nowiki", "pre"].iter().find_map(|name| {
+    let after = tail.get(..name.len()).filter(|it| it.eq_ignore_ascii_case(name)).map(|_| &tail[name.len()..])?;
+    if !after.starts_with(|c: char| c.is_whitespace() || c == '>') {
+      return None;
+    }
+    let close = format!("</{}>", name);
+    src
+      .match_indices("</")
+      .find(|(start, _)| src[*start..].get(..close.len()).is_some_and(|it| it.eq_ignore_ascii_case(&close)))
+      .map(|(start, _)| start + close.len())
+  })
+}
+
+/// What of `src` MediaWiki would read in `view`. Viewed directly, `<includeonly>` content is left
+/// out; transcluded, `<noinclude>` content is, and where there is any `<onlyinclude>` only what is
+/// inside one is read. The tags themselves always go. They nest, `</noinclude>` closes the innermost
+/// `<noinclude>`, and one never closed runs to the end; comments, `<nowiki>` and `<pre>` are kept
+/// as they are.
+pub fn include(src: &str, view: View) -> String {
+  // `<onlyinclude>` only matters when there is one
+  let only = view == View::Transcluded && {
+    let mut rest = src;
+    let mut found = false;
+    while let Some(start) = rest.find('<') {
+      rest = &rest[start..];
+      if let Some(size) = opaque(rest) {
+        rest = &rest[size..];
+      } else if let Some((Mark::OnlyInclude, false, false, _)) = include_tag(rest) {
+        found = true;
+        break;
+      } else {
+        rest = &rest[1..];
+      }
+    }
+    found
+  };
+  let hides = |include| match view {
+    View::Direct => include == Mark::IncludeOnly,
+    View::Transcluded => include == Mark::NoInclude,
+  };
+  let mut out = String::new();
+  let mut hidden = 0usize;
+  let mut inside = 0usize;
+  let mut rest = src;
+  loop {
+    let visible = hidden == 0 && (!only || inside > 0);
+    let start = rest.find('<').unwrap_or(rest.len());
+    if visible {
+      out += &rest[..start];
+    }
+    rest = &rest[start..];
+    if rest.is_empty() {
+      return out;
+    }
+    let size = if let Some(size) = opaque(rest) {
+      if visible {
+        out += &rest[..size];
+      }
+      size
+    } else if let Some((include, close, empty, size)) = include_tag(rest) {
+      let depth = match include {
+        Mark::OnlyInclude => Some(&mut inside),
+        include if hides(include) => Some(&mut hidden),
+        _ => None,
+      };
+      match (depth, close, empty) {
+        (Some(depth), false, false) => *depth += 1,
+        (Some(depth), true, false) => *depth = depth.saturating_sub(1),
+        _ => {}
+      }
+      size
+    } else {
+      if visible {
+        out.push('<');
+      }
+      1
+    };
+    rest = &rest[size..];
+  }
+}
+
+#[cfg(test)]
+#[test]
+fn test_include() {
+  let template = "{{a}}<noinclude>[[Category:x]]</noinclude><includeonly>b</includeonly><!-- <noinclude> -->c";
+  assert_eq!(include(template, View::Direct), "{{a}}[[Category:x]]<!-- <noinclude> -->c");
+  assert_eq!(include(template, View::Transcluded), "{{a}}b<!-- <noinclude> -->c");
+
+  let only = "a<onlyinclude>b<noinclude>c</noinclude><includeonly>d</includeonly></onlyinclude>e<ONLYINCLUDE>f";
+  assert_eq!(include(only, View::Transcluded), "bdf");
+  assert_eq!(include(only, View::Direct), "abcef");
+
+  // they nest, one left open runs to the end
+  let nested = "a<noinclude>b<noinclude>c</noinclude>d</noinclude>e<noinclude >f";
+  assert_eq!(include(nested, View::Transcluded), "ae");
+  assert_eq!(include(nested, View::Direct), "abcdef");
+  assert_eq!(
+    include("<nowiki><includeonly>x</includeonly></nowiki>y<br>", View::Direct),
+    "<nowiki><includeonly>x</includeonly></nowiki>y<br>"
+  );
+  assert_eq!(include("<includeonly/>a</includeonly>", View::Transcluded), "a");
+}
@@ -2,6 +2,7 @@ package org.apqm.jni
 
 final class NativeLib {
   @native def getPage(path: String, language: String): Unit
+  @native def runTests(module: String): Boolean
 }
 object NativeLib {
   // private[this]